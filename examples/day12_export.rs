@@ -0,0 +1,39 @@
+// dump the day 12 heightmap, with the shortest path from S to E drawn on it, as an image and
+// two meshes: <out>.pgm, <out>.obj and <out>.stl
+//
+//   cargo run --example day12_export -- input/2022/day12.txt day12
+use aoc_2022_rust::day12;
+use std::fs::File;
+use std::io::BufWriter;
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let mut args = std::env::args().skip(1);
+    let (Some(input), out) = (args.next(), args.next()) else {
+        return Err("usage: day12_export <input> [<out>]".into());
+    };
+    let out = out.unwrap_or_else(|| "day12".to_string());
+    let heightmap = day12::generator(&std::fs::read_to_string(input)?)?;
+    // no path is still worth looking at
+    let path = day12::shortest_path(&heightmap).unwrap_or_default();
+    day12::write_pgm(
+        &heightmap,
+        &path,
+        &mut BufWriter::new(File::create(format!("{}.pgm", out))?),
+    )?;
+    day12::write_obj(
+        &heightmap,
+        &path,
+        &mut BufWriter::new(File::create(format!("{}.obj", out))?),
+    )?;
+    day12::write_stl(
+        &heightmap,
+        &path,
+        &mut BufWriter::new(File::create(format!("{}.stl", out))?),
+    )?;
+    println!(
+        "wrote {0}.pgm, {0}.obj and {0}.stl ({1} steps)",
+        out,
+        path.len().saturating_sub(1)
+    );
+    Ok(())
+}
@@ -3,30 +3,89 @@ use std::fmt::Write;
 
 pub type Point = (usize, usize);
 
-pub type Input = (Point, Point, HashMap<Point, usize>);
+// start points, goal points, elevation of every point
+pub type Input = (Vec<Point>, Vec<Point>, HashMap<Point, usize>);
 
-pub fn generator(input: &str) -> Input {
-    use aoc_parse::{parser, prelude::*};
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseError {
+    UnexpectedChar(char, Point),
+    MissingStart,
+    MissingGoal,
+    DuplicateStart(Point, Point),
+    DuplicateGoal(Point, Point),
+    // the same marker was given as both a start and a goal marker
+    AmbiguousMarker(char),
+}
+
+impl std::fmt::Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ParseError::UnexpectedChar(c, p) => write!(f, "unexpected {:?} at {:?}", c, p),
+            ParseError::MissingStart => write!(f, "no start marker"),
+            ParseError::MissingGoal => write!(f, "no goal marker"),
+            ParseError::DuplicateStart(a, b) => {
+                write!(f, "more than one start: {:?} and {:?}", a, b)
+            }
+            ParseError::DuplicateGoal(a, b) => write!(f, "more than one goal: {:?} and {:?}", a, b),
+            ParseError::AmbiguousMarker(c) => {
+                write!(f, "{:?} is both a start and a goal marker", c)
+            }
+        }
+    }
+}
 
-    let data = parser!(lines(alpha+)).parse(input).unwrap();
+impl std::error::Error for ParseError {}
+
+// the puzzle input: exactly one S and one E
+pub fn generator(input: &str) -> Result<Input, ParseError> {
+    let (start, end, map) = generator_with_markers(input, &['S'], &['E'])?;
+    if let [a, b, ..] = start[..] {
+        return Err(ParseError::DuplicateStart(a, b));
+    }
+    if let [a, b, ..] = end[..] {
+        return Err(ParseError::DuplicateGoal(a, b));
+    }
+    Ok((start, end, map))
+}
+
+// any cell marked with one of `starts` is a start, and any cell marked with one of `goals` is a
+// goal, there may be any number of each (but at least one). Markers that are letters keep their
+// elevation, so eg. `starts = ['S', 'a']` searches from every lowest point. Other markers are at
+// the bottom (starts) or the top (goals), like S and E
+pub fn generator_with_markers(
+    input: &str,
+    starts: &[char],
+    goals: &[char],
+) -> Result<Input, ParseError> {
+    if let Some(c) = starts.iter().find(|c| goals.contains(c)) {
+        return Err(ParseError::AmbiguousMarker(*c));
+    }
+    // elevation is a..z, where z is the highest
+    let elevation = |c: char| c.is_ascii_lowercase().then(|| c as usize - 'a' as usize);
     let mut map = HashMap::new();
-    let mut start = (0, 0);
-    let mut end = (0, 0);
-    for (i, line) in data.iter().enumerate() {
-        for (j, c) in line.iter().enumerate() {
-            if c == &'S' {
-                start = (i, j);
-                map.insert((i, j), 0);
-            } else if c == &'E' {
-                end = (i, j);
-                map.insert((i, j), 25);
+    let mut start = vec![];
+    let mut end = vec![];
+    for (i, line) in input.lines().enumerate() {
+        for (j, c) in line.trim_end().chars().enumerate() {
+            let z = if starts.contains(&c) {
+                start.push((i, j));
+                elevation(c).unwrap_or(0)
+            } else if goals.contains(&c) {
+                end.push((i, j));
+                elevation(c).unwrap_or(25)
             } else {
-                // elevation is a..z, where z is the highest
-                map.insert((i, j), *c as usize - 'a' as usize);
-            }
+                elevation(c).ok_or(ParseError::UnexpectedChar(c, (i, j)))?
+            };
+            map.insert((i, j), z);
         }
     }
-    (start, end, map)
+    if start.is_empty() {
+        return Err(ParseError::MissingStart);
+    }
+    if end.is_empty() {
+        return Err(ParseError::MissingGoal);
+    }
+    Ok((start, end, map))
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
@@ -49,7 +108,18 @@ impl std::cmp::PartialOrd for Node {
     }
 }
 
-// visited = grey, heap = green (using ANSI codes)
+// up, down, left, right. Stepping off the top/left edge wraps to usize::MAX, which is never in the map
+fn neighbours((i, j): Point) -> [Point; 4] {
+    [
+        (i.wrapping_sub(1), j),
+        (i + 1, j),
+        (i, j.wrapping_sub(1)),
+        (i, j + 1),
+    ]
+}
+
+// visited = grey, heap = green (using ANSI codes). Sleeps on every frame, so only hook it up when debugging
+#[allow(dead_code)]
 fn print_grid(
     input: &HashMap<(usize, usize), usize>,
     visited: &std::collections::HashSet<(usize, usize)>,
@@ -85,40 +155,45 @@ fn print_grid(
     std::thread::sleep(std::time::Duration::from_millis(1000 / 24));
 }
 
-// find the length of the shortest path from (0, 0) to the highest point (z).
+// find the length of the shortest path from a start to a goal (the highest point, z).
 // we can only move to a point that is at most 1 higher than our current point.
-pub fn part_1((start, end, input): &Input) -> usize {
+pub fn part_1((starts, goals, input): &Input) -> usize {
     let mut heap = std::collections::BinaryHeap::new();
-    heap.push(Node {
-        i: start.0,
-        j: start.1,
-        dist: 0,
-        estimated_dist: 0,
-    });
+    for start in starts {
+        heap.push(Node {
+            i: start.0,
+            j: start.1,
+            dist: 0,
+            estimated_dist: 0,
+        });
+    }
+    // distance to the nearest goal
     let heuristic = |Node { i, j, .. }| {
-        ((i as isize - end.0 as isize).abs() + (j as isize - end.1 as isize).abs()) as usize
+        goals
+            .iter()
+            .map(|end| {
+                ((i as isize - end.0 as isize).abs() + (j as isize - end.1 as isize).abs()) as usize
+            })
+            .min()
+            .unwrap()
     };
+    // nb: a point is only done once it's popped. The first time we see a point isn't necessarily
+    // along the shortest path to it (this showed up once there could be more than one start)
     let mut visited = std::collections::HashSet::new();
-    visited.insert(*start);
     while let Some(Node { i, j, dist, .. }) = heap.pop() {
-        print_grid(input, &visited, &heap);
-        if (i, j) == *end {
+        // print_grid(input, &visited, &heap);
+        if !visited.insert((i, j)) {
+            continue;
+        }
+        if goals.contains(&(i, j)) {
             return dist;
         }
-        // let neighbours = [(i - 1, j), (i + 1, j), (i, j - 1), (i, j + 1)];
-        let neighbours = [
-            (i.wrapping_sub(1), j + 1),
-            (i + 1, j + 1),
-            (i, j.wrapping_sub(1)),
-            (i, j + 1),
-        ];
-        for (i2, j2) in neighbours.into_iter() {
+        for (i2, j2) in neighbours((i, j)).into_iter() {
             if visited.contains(&(i2, j2)) {
                 continue;
             }
             if let Some(&z) = input.get(&(i2, j2)) {
                 if z <= input[&(i, j)] + 1 {
-                    visited.insert((i2, j2));
                     heap.push(Node {
                         i: i2,
                         j: j2,
@@ -139,31 +214,26 @@ pub fn part_1((start, end, input): &Input) -> usize {
     panic!("no path found")
 }
 
-pub fn part_1_dijkstras((start, end, input): &Input) -> usize {
+pub fn part_1_dijkstras((starts, goals, input): &Input) -> usize {
     let mut heap = std::collections::BinaryHeap::new();
-    heap.push(Node {
-        i: start.0,
-        j: start.1,
-        dist: 0,
-        estimated_dist: 0,
-    });
+    for start in starts {
+        heap.push(Node {
+            i: start.0,
+            j: start.1,
+            dist: 0,
+            estimated_dist: 0,
+        });
+    }
     // a star with a zero heuristic is dijkstras
     let heuristic = |Node { i, j, .. }| 0;
     let mut visited = std::collections::HashSet::new();
-    visited.insert(*start);
+    visited.extend(starts.iter().cloned());
     while let Some(Node { i, j, dist, .. }) = heap.pop() {
-        print_grid(input, &visited, &heap);
-        if (i, j) == *end {
+        // print_grid(input, &visited, &heap);
+        if goals.contains(&(i, j)) {
             return dist;
         }
-        // let neighbours = [(i - 1, j), (i + 1, j), (i, j - 1), (i, j + 1)];
-        let neighbours = [
-            (i.wrapping_sub(1), j + 1),
-            (i + 1, j + 1),
-            (i, j.wrapping_sub(1)),
-            (i, j + 1),
-        ];
-        for (i2, j2) in neighbours.into_iter() {
+        for (i2, j2) in neighbours((i, j)).into_iter() {
             if visited.contains(&(i2, j2)) {
                 continue;
             }
@@ -190,30 +260,93 @@ pub fn part_1_dijkstras((start, end, input): &Input) -> usize {
     panic!("no path found")
 }
 
+// search from both ends at once: forwards from the starts, and backwards from the goals with the
+// reversed rule from part 2. Always expand a whole layer of whichever frontier is smaller; the
+// first layer where the two searches touch contains the shortest path
+pub fn part_1_bidirectional((starts, goals, input): &Input) -> usize {
+    if starts.iter().any(|start| goals.contains(start)) {
+        return 0;
+    }
+    let mut forward: HashMap<_, _> = starts.iter().map(|&start| (start, 0)).collect();
+    let mut backward: HashMap<_, _> = goals.iter().map(|&end| (end, 0)).collect();
+    let mut forward_frontier = starts.clone();
+    let mut backward_frontier = goals.clone();
+    while !forward_frontier.is_empty() && !backward_frontier.is_empty() {
+        let meeting = if forward_frontier.len() <= backward_frontier.len() {
+            expand_layer(
+                &mut forward_frontier,
+                &mut forward,
+                &backward,
+                input,
+                |a, b| b <= a + 1,
+            )
+        } else {
+            expand_layer(
+                &mut backward_frontier,
+                &mut backward,
+                &forward,
+                input,
+                |a, b| a <= b + 1,
+            )
+        };
+        if let Some(dist) = meeting {
+            return dist;
+        }
+    }
+    panic!("no path found")
+}
+
+// replace the frontier with the next layer out. If any of the new points have already been seen
+// by the other search, return the shortest total distance through them
+fn expand_layer(
+    frontier: &mut Vec<Point>,
+    seen: &mut HashMap<Point, usize>,
+    other: &HashMap<Point, usize>,
+    input: &HashMap<Point, usize>,
+    can_step: fn(usize, usize) -> bool,
+) -> Option<usize> {
+    let mut next_frontier = vec![];
+    let mut best: Option<usize> = None;
+    for current in frontier.drain(..) {
+        let dist = seen[&current] + 1;
+        for next in neighbours(current) {
+            if seen.contains_key(&next) {
+                continue;
+            }
+            if let Some(&z) = input.get(&next) {
+                if can_step(input[&current], z) {
+                    seen.insert(next, dist);
+                    next_frontier.push(next);
+                    if let Some(rest) = other.get(&next) {
+                        best = Some(best.map_or(dist + rest, |b| b.min(dist + rest)));
+                    }
+                }
+            }
+        }
+    }
+    *frontier = next_frontier;
+    best
+}
+
 // part 2 is similar to part 1, but now we want to find the nearest tile to the end of height 0
-pub fn part_2((_start, end, input): &Input) -> usize {
+pub fn part_2((_starts, goals, input): &Input) -> usize {
     let mut heap = std::collections::BinaryHeap::new();
-    heap.push(Node {
-        i: end.0,
-        j: end.1,
-        dist: 0,
-        estimated_dist: 0,
-    });
+    for end in goals {
+        heap.push(Node {
+            i: end.0,
+            j: end.1,
+            dist: 0,
+            estimated_dist: 0,
+        });
+    }
     let mut visited = std::collections::HashSet::new();
-    visited.insert(*end);
+    visited.extend(goals.iter().cloned());
     while let Some(Node { i, j, dist, .. }) = heap.pop() {
-        print_grid(input, &visited, &heap);
+        // print_grid(input, &visited, &heap);
         if input[&(i, j)] == 0 {
             return dist;
         }
-        // let neighbours = [(i - 1, j), (i + 1, j), (i, j - 1), (i, j + 1)];
-        let neighbours = [
-            (i.wrapping_sub(1), j + 1),
-            (i + 1, j + 1),
-            (i, j.wrapping_sub(1)),
-            (i, j + 1),
-        ];
-        for (i2, j2) in neighbours.into_iter() {
+        for (i2, j2) in neighbours((i, j)).into_iter() {
             if visited.contains(&(i2, j2)) {
                 continue;
             }
@@ -234,9 +367,492 @@ pub fn part_2((_start, end, input): &Input) -> usize {
     panic!("no path found")
 }
 
+// same search as part 1, but a plain BFS that remembers where each point was reached from,
+// so we can walk back from the goal and get the actual path (start and goal included).
+// the starts are marked as coming from themselves
+pub fn shortest_path((starts, goals, input): &Input) -> Option<Vec<Point>> {
+    let mut came_from = HashMap::new();
+    let mut queue = std::collections::VecDeque::new();
+    for &start in starts {
+        queue.push_back(start);
+        came_from.insert(start, start);
+    }
+    while let Some(current) = queue.pop_front() {
+        if goals.contains(&current) {
+            let mut path = vec![current];
+            let mut current = current;
+            while came_from[&current] != current {
+                current = came_from[&current];
+                path.push(current);
+            }
+            path.reverse();
+            return Some(path);
+        }
+        for next in neighbours(current) {
+            if came_from.contains_key(&next) {
+                continue;
+            }
+            if let Some(&z) = input.get(&next) {
+                if z <= input[&current] + 1 {
+                    came_from.insert(next, current);
+                    queue.push_back(next);
+                }
+            }
+        }
+    }
+    None
+}
+
+// BFS distance from the nearest of `from` to every point reachable. `can_step(a, b)` decides if
+// we can move from height a to height b, so the same search works both forwards and in reverse
+fn distances(
+    from: &[Point],
+    input: &HashMap<Point, usize>,
+    can_step: impl Fn(usize, usize) -> bool,
+) -> HashMap<Point, usize> {
+    let mut dist = HashMap::new();
+    let mut queue = std::collections::VecDeque::new();
+    for &p in from {
+        dist.insert(p, 0);
+        queue.push_back(p);
+    }
+    while let Some(current) = queue.pop_front() {
+        for next in neighbours(current) {
+            if dist.contains_key(&next) {
+                continue;
+            }
+            if let Some(&z) = input.get(&next) {
+                if can_step(input[&current], z) {
+                    dist.insert(next, dist[&current] + 1);
+                    queue.push_back(next);
+                }
+            }
+        }
+    }
+    dist
+}
+
+// every shortest path from any start to any goal, as a DAG: a cell is on some shortest path iff
+// its distance from the starts plus its distance to the goals is the shortest distance, and
+// every step of such a path goes from one of those cells to another, one closer to a goal
+pub struct ShortestPaths {
+    // the starts and goals that are at the ends of some shortest path
+    starts: Vec<Point>,
+    goals: Vec<Point>,
+    distance: usize,
+    // cell -> next cells on a shortest path
+    edges: HashMap<Point, Vec<Point>>,
+    // cell -> distance from the start
+    depth: HashMap<Point, usize>,
+}
+
+pub fn shortest_paths((starts, goals, input): &Input) -> Option<ShortestPaths> {
+    let from_start = distances(starts, input, |a, b| b <= a + 1);
+    let to_end = distances(goals, input, |a, b| a <= b + 1);
+    let distance = goals
+        .iter()
+        .filter_map(|p| from_start.get(p))
+        .min()
+        .cloned()?;
+    let on_path = |p: &Point| match (from_start.get(p), to_end.get(p)) {
+        (Some(a), Some(b)) => a + b == distance,
+        _ => false,
+    };
+    let edges: HashMap<_, Vec<_>> = from_start
+        .keys()
+        .filter(|p| on_path(p))
+        .map(|&p| {
+            let next = neighbours(p)
+                .into_iter()
+                .filter(|n| on_path(n) && from_start[n] == from_start[&p] + 1)
+                .filter(|n| input[n] <= input[&p] + 1)
+                .collect();
+            (p, next)
+        })
+        .collect();
+    let depth = edges.keys().map(|p| (*p, from_start[p])).collect();
+    let starts = starts.iter().filter(|p| on_path(p)).cloned().collect();
+    let goals = goals.iter().filter(|p| on_path(p)).cloned().collect();
+    Some(ShortestPaths {
+        starts,
+        goals,
+        distance,
+        edges,
+        depth,
+    })
+}
+
+impl ShortestPaths {
+    // number of steps in each of the paths (same as part 1)
+    pub fn distance(&self) -> usize {
+        self.distance
+    }
+
+    // the number of paths can grow exponentially with the size of the map, so use a bigint.
+    // paths to a cell = sum of paths to the cells leading into it, walked in distance order
+    pub fn count(&self) -> num_bigint::BigUint {
+        let mut order = self.edges.keys().collect::<Vec<_>>();
+        order.sort_by_key(|p| self.depth[p]);
+        let mut counts: HashMap<Point, num_bigint::BigUint> = HashMap::new();
+        for start in &self.starts {
+            counts.insert(*start, 1u32.into());
+        }
+        for p in order {
+            let count = counts.get(p).cloned().unwrap_or_default();
+            for next in &self.edges[p] {
+                *counts.entry(*next).or_default() += &count;
+            }
+        }
+        self.goals.iter().filter_map(|p| counts.remove(p)).sum()
+    }
+
+    // the cells that lie on at least one shortest path
+    pub fn cells(&self) -> std::collections::HashSet<Point> {
+        self.edges.keys().cloned().collect()
+    }
+
+    // lazily walk every path, depth first, one start at a time
+    pub fn iter(&self) -> PathIter<'_> {
+        PathIter {
+            paths: self,
+            starts: self.starts.iter(),
+            stack: vec![],
+        }
+    }
+}
+
+pub struct PathIter<'a> {
+    paths: &'a ShortestPaths,
+    // the starts we haven't walked from yet
+    starts: std::slice::Iter<'a, Point>,
+    // the current path, and which of the next cells to try when we backtrack to each point
+    stack: Vec<(Point, usize)>,
+}
+
+impl Iterator for PathIter<'_> {
+    type Item = Vec<Point>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.stack.is_empty() {
+            self.stack.push((*self.starts.next()?, 0));
+        }
+        while let Some(&mut (p, ref mut idx)) = self.stack.last_mut() {
+            if self.paths.goals.contains(&p) {
+                let path = self.stack.iter().map(|(p, _)| *p).collect();
+                self.stack.pop();
+                return Some(path);
+            }
+            match self.paths.edges[&p].get(*idx) {
+                Some(&next) => {
+                    *idx += 1;
+                    self.stack.push((next, 0));
+                }
+                None => {
+                    self.stack.pop();
+                    if self.stack.is_empty() {
+                        self.stack.push((*self.starts.next()?, 0));
+                    }
+                }
+            }
+        }
+        None
+    }
+}
+
+// Lifelong Planning A* (LPA*): keeps the search state from the last run around, so after
+// changing a few heights only the part of the search that depended on them is redone.
+// g is the distance we've settled on for a point, rhs is what it should be according to its
+// predecessors (min g + 1). Points where those disagree are "inconsistent" and sit in the queue
+// until fixed. Anything not in g/rhs is at infinity.
+pub struct IncrementalPlanner {
+    starts: Vec<Point>,
+    goals: Vec<Point>,
+    heights: HashMap<Point, usize>,
+    g: HashMap<Point, usize>,
+    rhs: HashMap<Point, usize>,
+    heap: std::collections::BinaryHeap<std::cmp::Reverse<(Key, Point)>>,
+    // the current key of each queued point, heap entries that don't match are stale
+    queued: HashMap<Point, Key>,
+    // number of points expanded so far, to see how much work an update saved
+    expanded: usize,
+}
+
+// (min(g, rhs) + heuristic, min(g, rhs)), compared lexicographically
+type Key = (usize, usize);
+
+const INFINITY: usize = usize::MAX;
+
+impl IncrementalPlanner {
+    pub fn new((starts, goals, input): &Input) -> Self {
+        let mut planner = IncrementalPlanner {
+            starts: starts.clone(),
+            goals: goals.clone(),
+            heights: input.clone(),
+            g: HashMap::new(),
+            rhs: HashMap::new(),
+            heap: std::collections::BinaryHeap::new(),
+            queued: HashMap::new(),
+            expanded: 0,
+        };
+        for &start in starts {
+            planner.rhs.insert(start, 0);
+            planner.enqueue(start);
+        }
+        planner.compute_shortest_path();
+        planner
+    }
+
+    // length of the shortest path from any start to any goal, if there is one
+    pub fn distance(&self) -> Option<usize> {
+        Some(self.goals.iter().map(|&p| self.g(p)).min().unwrap()).filter(|&d| d != INFINITY)
+    }
+
+    // change the height of a single cell, and return the new shortest distance
+    pub fn set_height(&mut self, p: Point, z: usize) -> Option<usize> {
+        let old = self.heights.insert(p, z);
+        assert!(old.is_some(), "point outside of the map: {:?}", p);
+        // the edges into and out of p changed, which affects the rhs of p and of its neighbours
+        self.update(p);
+        for n in neighbours(p) {
+            if self.heights.contains_key(&n) {
+                self.update(n);
+            }
+        }
+        self.compute_shortest_path();
+        self.distance()
+    }
+
+    fn g(&self, p: Point) -> usize {
+        self.g.get(&p).cloned().unwrap_or(INFINITY)
+    }
+
+    fn rhs(&self, p: Point) -> usize {
+        self.rhs.get(&p).cloned().unwrap_or(INFINITY)
+    }
+
+    fn key(&self, p: Point) -> Key {
+        let (i, j) = p;
+        let heuristic = self
+            .goals
+            .iter()
+            .map(|end| i.abs_diff(end.0) + j.abs_diff(end.1))
+            .min()
+            .unwrap();
+        let best = self.g(p).min(self.rhs(p));
+        (best.saturating_add(heuristic), best)
+    }
+
+    fn can_step(&self, from: Point, to: Point) -> bool {
+        match (self.heights.get(&from), self.heights.get(&to)) {
+            (Some(&a), Some(&b)) => b <= a + 1,
+            _ => false,
+        }
+    }
+
+    fn enqueue(&mut self, p: Point) {
+        let key = self.key(p);
+        self.queued.insert(p, key);
+        self.heap.push(std::cmp::Reverse((key, p)));
+    }
+
+    // smallest key in the queue, dropping stale heap entries on the way
+    fn top(&mut self) -> Option<(Key, Point)> {
+        while let Some(&std::cmp::Reverse((key, p))) = self.heap.peek() {
+            if self.queued.get(&p) == Some(&key) {
+                return Some((key, p));
+            }
+            self.heap.pop();
+        }
+        None
+    }
+
+    // recompute rhs of p from its predecessors, and (de)queue p depending on whether it's consistent
+    fn update(&mut self, p: Point) {
+        if !self.starts.contains(&p) {
+            let rhs = neighbours(p)
+                .into_iter()
+                .filter(|&n| self.can_step(n, p))
+                .map(|n| self.g(n).saturating_add(1))
+                .min()
+                .unwrap_or(INFINITY);
+            self.rhs.insert(p, rhs);
+        }
+        if self.g(p) != self.rhs(p) {
+            self.enqueue(p);
+        } else {
+            self.queued.remove(&p);
+        }
+    }
+
+    fn compute_shortest_path(&mut self) {
+        while let Some((key, p)) = self.top() {
+            // done once the most promising goal is settled, and nothing queued could beat it
+            let end = *self.goals.iter().min_by_key(|&&p| self.key(p)).unwrap();
+            if key >= self.key(end) && self.g(end) == self.rhs(end) {
+                break;
+            }
+            self.heap.pop();
+            self.queued.remove(&p);
+            self.expanded += 1;
+            if self.g(p) > self.rhs(p) {
+                // overconsistent: we found a shorter way here, settle it
+                self.g.insert(p, self.rhs(p));
+            } else {
+                // underconsistent: the old path got longer or disappeared, start over for p
+                self.g.insert(p, INFINITY);
+                self.update(p);
+            }
+            for n in neighbours(p) {
+                if self.can_step(p, n) {
+                    self.update(n);
+                }
+            }
+        }
+    }
+}
+
+// (rows, columns) of the heightmap
+fn dimensions(input: &HashMap<Point, usize>) -> (usize, usize) {
+    input.keys().fold((0, 0), |(rows, cols), &(i, j)| {
+        (rows.max(i + 1), cols.max(j + 1))
+    })
+}
+
+// binary greyscale PGM (P5), one pixel per cell. Heights a..z map to 0..200, and the path is
+// drawn in full white on top so it stands out from the highest terrain
+pub fn write_pgm(
+    (_, _, input): &Input,
+    path: &[Point],
+    out: &mut impl std::io::Write,
+) -> std::io::Result<()> {
+    let (rows, cols) = dimensions(input);
+    let path: std::collections::HashSet<_> = path.iter().collect();
+    write!(out, "P5\n{} {}\n255\n", cols, rows)?;
+    let mut pixels = Vec::with_capacity(rows * cols);
+    for i in 0..rows {
+        for j in 0..cols {
+            pixels.push(if path.contains(&(i, j)) {
+                255
+            } else {
+                input.get(&(i, j)).map_or(0, |&z| (z * 8) as u8)
+            });
+        }
+    }
+    out.write_all(&pixels)
+}
+
+type Vertex = [f32; 3];
+type Quad = [Vertex; 4];
+
+// the six faces of an axis-aligned box, wound counter-clockwise when seen from outside
+fn cuboid([x0, y0, z0]: Vertex, [x1, y1, z1]: Vertex) -> [Quad; 6] {
+    [
+        [[x0, y0, z0], [x0, y1, z0], [x1, y1, z0], [x1, y0, z0]], // bottom
+        [[x0, y0, z1], [x1, y0, z1], [x1, y1, z1], [x0, y1, z1]], // top
+        [[x0, y0, z0], [x1, y0, z0], [x1, y0, z1], [x0, y0, z1]], // front
+        [[x0, y1, z0], [x0, y1, z1], [x1, y1, z1], [x1, y1, z0]], // back
+        [[x0, y0, z0], [x0, y0, z1], [x0, y1, z1], [x0, y1, z0]], // left
+        [[x1, y0, z0], [x1, y1, z0], [x1, y1, z1], [x1, y0, z1]], // right
+    ]
+}
+
+// one unit column per cell (x = column, y = row, z up), 'a' being one unit tall so the lowest
+// cells are still visible. Path cells get a small marker block on top of their column
+fn mesh((_, _, input): &Input, path: &[Point]) -> (Vec<Quad>, Vec<Quad>) {
+    let mut cells = input.iter().collect::<Vec<_>>();
+    cells.sort();
+    let terrain = cells
+        .into_iter()
+        .flat_map(|(&(i, j), &z)| {
+            let (x, y) = (j as f32, i as f32);
+            cuboid([x, y, 0.0], [x + 1.0, y + 1.0, z as f32 + 1.0])
+        })
+        .collect();
+    let markers = path
+        .iter()
+        .flat_map(|&(i, j)| {
+            let (x, y, z) = (j as f32, i as f32, input[&(i, j)] as f32 + 1.0);
+            cuboid([x + 0.25, y + 0.25, z], [x + 0.75, y + 0.75, z + 0.25])
+        })
+        .collect();
+    (terrain, markers)
+}
+
+// Wavefront OBJ with the terrain and the path as separate objects
+pub fn write_obj(
+    input: &Input,
+    path: &[Point],
+    out: &mut impl std::io::Write,
+) -> std::io::Result<()> {
+    let (terrain, markers) = mesh(input, path);
+    let mut index = 1;
+    for (name, quads) in [("terrain", terrain), ("path", markers)] {
+        writeln!(out, "o {}", name)?;
+        for quad in quads.iter() {
+            for [x, y, z] in quad {
+                writeln!(out, "v {} {} {}", x, y, z)?;
+            }
+        }
+        for _ in quads.iter() {
+            writeln!(out, "f {} {} {} {}", index, index + 1, index + 2, index + 3)?;
+            index += 4;
+        }
+    }
+    Ok(())
+}
+
+// ASCII STL, again with the terrain and the path as separate solids. STL only does triangles,
+// so every quad is split in two
+pub fn write_stl(
+    input: &Input,
+    path: &[Point],
+    out: &mut impl std::io::Write,
+) -> std::io::Result<()> {
+    let (terrain, markers) = mesh(input, path);
+    for (name, quads) in [("terrain", terrain), ("path", markers)] {
+        writeln!(out, "solid {}", name)?;
+        for [a, b, c, d] in quads {
+            let (u, v) = (
+                [b[0] - a[0], b[1] - a[1], b[2] - a[2]],
+                [c[0] - b[0], c[1] - b[1], c[2] - b[2]],
+            );
+            let normal = [
+                u[1] * v[2] - u[2] * v[1],
+                u[2] * v[0] - u[0] * v[2],
+                u[0] * v[1] - u[1] * v[0],
+            ];
+            let len = (normal[0].powi(2) + normal[1].powi(2) + normal[2].powi(2)).sqrt();
+            for triangle in [[a, b, c], [a, c, d]] {
+                writeln!(
+                    out,
+                    "  facet normal {} {} {}",
+                    normal[0] / len,
+                    normal[1] / len,
+                    normal[2] / len
+                )?;
+                writeln!(out, "    outer loop")?;
+                for [x, y, z] in triangle {
+                    writeln!(out, "      vertex {} {} {}", x, y, z)?;
+                }
+                writeln!(out, "    endloop")?;
+                writeln!(out, "  endfacet")?;
+            }
+        }
+        writeln!(out, "endsolid {}", name)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
 mod test {
     use super::*;
 
+    const SAMPLE_INPUT: &str = "Sabqponm\n\
+    abcryxxl\n\
+    accszExk\n\
+    acctuvwj\n\
+    abdefghi\n";
+
     #[test]
     fn test_generator() {
         let input = "abcde\n\
@@ -260,7 +876,8 @@ mod test {
             ((4, 2), 14),
         ]);
         let keys = expected.keys().cloned().collect::<Vec<_>>();
-        let (_, _, actual) = generator(input);
+        // no S or E in here, use the first and last letters instead
+        let (_, _, actual) = generator_with_markers(input, &['a'], &['o']).unwrap();
         assert_eq!(actual.len(), expected.len(), "lengths differ");
         for k in keys {
             // TODO: It's easier to flip the key, but I should really fix the expected hashmap
@@ -268,4 +885,280 @@ mod test {
             assert_eq!(actual.get(&flipped), expected.get(&k), "key: {:?}", k);
         }
     }
+
+    #[test]
+    fn test_shortest_path() {
+        let input = generator(SAMPLE_INPUT).unwrap();
+        let path = shortest_path(&input).unwrap();
+        assert_eq!(path.len(), 31 + 1);
+        assert_eq!(path.first(), Some(&input.0[0]));
+        assert_eq!(path.last(), Some(&input.1[0]));
+    }
+
+    #[test]
+    fn test_write_pgm() {
+        let input = generator(SAMPLE_INPUT).unwrap();
+        let path = shortest_path(&input).unwrap();
+        let mut out = vec![];
+        write_pgm(&input, &path, &mut out).unwrap();
+        let header = b"P5\n8 5\n255\n";
+        assert_eq!(&out[..header.len()], header);
+        let pixels = &out[header.len()..];
+        assert_eq!(pixels.len(), 8 * 5);
+        assert_eq!(pixels.iter().filter(|&&p| p == 255).count(), path.len());
+        for (&(i, j), &z) in input.2.iter() {
+            if !path.contains(&(i, j)) {
+                assert_eq!(pixels[i * 8 + j], (z * 8) as u8, "pixel: {:?}", (i, j));
+            }
+        }
+    }
+
+    #[test]
+    fn test_write_obj() {
+        let input = generator(SAMPLE_INPUT).unwrap();
+        let path = shortest_path(&input).unwrap();
+        let mut out = vec![];
+        write_obj(&input, &path, &mut out).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        // a box per cell, and a marker per path cell
+        let boxes = 8 * 5 + path.len();
+        assert_eq!(
+            out.lines().filter(|l| l.starts_with("v ")).count(),
+            boxes * 6 * 4
+        );
+        assert_eq!(
+            out.lines().filter(|l| l.starts_with("f ")).count(),
+            boxes * 6
+        );
+        assert_eq!(
+            out.lines().last(),
+            Some(
+                format!(
+                    "f {} {} {} {}",
+                    boxes * 24 - 3,
+                    boxes * 24 - 2,
+                    boxes * 24 - 1,
+                    boxes * 24
+                )
+                .as_str()
+            )
+        );
+    }
+
+    #[test]
+    fn test_write_stl() {
+        let input = generator(SAMPLE_INPUT).unwrap();
+        let path = shortest_path(&input).unwrap();
+        let mut out = vec![];
+        write_stl(&input, &path, &mut out).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        let boxes = 8 * 5 + path.len();
+        assert_eq!(out.matches("facet normal").count(), boxes * 6 * 2);
+        assert!(out.starts_with("solid terrain\n"));
+        assert!(out.ends_with("endsolid path\n"));
+        // the top of every column faces straight up
+        assert!(out.contains("facet normal 0 0 1\n"));
+    }
+
+    #[test]
+    fn test_shortest_paths() {
+        // switching rows is allowed at any column, so there's one path per column
+        let input = generator(
+            "Sbcdefghijklmnopqrstuvwxyz\n\
+            abcdefghijklmnopqrstuvwxyE\n",
+        )
+        .unwrap();
+        let paths = shortest_paths(&input).unwrap();
+        assert_eq!(paths.distance(), 26);
+        assert_eq!(paths.count(), 26u32.into());
+        assert_eq!(paths.cells().len(), 2 * 26);
+        let all = paths.iter().collect::<std::collections::HashSet<_>>();
+        assert_eq!(all.len(), 26);
+        assert!(all.iter().all(|path| path.len() == 27));
+    }
+
+    #[test]
+    fn test_shortest_paths_sample() {
+        let input = generator(SAMPLE_INPUT).unwrap();
+        let paths = shortest_paths(&input).unwrap();
+        assert_eq!(paths.distance(), 31);
+        let all = paths.iter().collect::<Vec<_>>();
+        assert_eq!(paths.count(), all.len().into());
+        assert!(all.contains(&shortest_path(&input).unwrap()));
+        let cells = all.iter().flatten().cloned().collect();
+        assert_eq!(paths.cells(), cells);
+        for path in all {
+            assert_eq!(path.len(), 32);
+            for step in path.windows(2) {
+                assert!(neighbours(step[0]).contains(&step[1]));
+                assert!(input.2[&step[1]] <= input.2[&step[0]] + 1);
+            }
+        }
+    }
+
+    #[test]
+    fn test_shortest_paths_unreachable() {
+        let input = generator("SbcE\n").unwrap();
+        assert!(shortest_paths(&input).is_none());
+    }
+
+    #[test]
+    fn test_incremental_planner() {
+        // a flat field (E flattened too) where raising a cell to 5 turns it into a wall
+        let mut input = generator(
+            "Saaaaaaa\n\
+            aaaaaaaa\n\
+            aaaaaaaa\n\
+            aaaaaaaa\n\
+            aaaaaaaE\n",
+        )
+        .unwrap();
+        input.2.insert(input.1[0], 0);
+        let mut planner = IncrementalPlanner::new(&input);
+        assert_eq!(planner.distance(), Some(part_1(&input)));
+        let edits = [
+            // a wall across most of column 3 and back up column 6, forcing a zig-zag
+            ((0, 3), 5),
+            ((1, 3), 5),
+            ((2, 3), 5),
+            ((3, 3), 5),
+            ((4, 6), 5),
+            ((3, 6), 5),
+            ((2, 6), 5),
+            ((1, 6), 5),
+            // knock a hole in the first wall, then close it again
+            ((2, 3), 0),
+            ((2, 3), 5),
+            // climbing by one is still fine
+            ((4, 3), 1),
+            ((4, 4), 2),
+        ];
+        for (p, z) in edits {
+            input.2.insert(p, z);
+            assert_eq!(
+                planner.set_height(p, z),
+                Some(part_1(&input)),
+                "{:?}",
+                (p, z)
+            );
+        }
+        // close the last gap
+        input.2.insert((0, 6), 5);
+        assert_eq!(planner.set_height((0, 6), 5), None);
+        assert!(shortest_path(&input).is_none());
+    }
+
+    #[test]
+    fn test_incremental_planner_reuses_search() {
+        let input = generator(SAMPLE_INPUT).unwrap();
+        let mut planner = IncrementalPlanner::new(&input);
+        let initial = planner.expanded;
+        // a change off in the corner, away from the path, shouldn't need a full search
+        planner.set_height((4, 0), 1);
+        assert_eq!(planner.distance(), Some(31));
+        assert!(planner.expanded - initial < initial / 4);
+    }
+
+    #[test]
+    fn test_part_1_bidirectional() {
+        assert_eq!(part_1_bidirectional(&generator(SAMPLE_INPUT).unwrap()), 31);
+        // the zig-zag from the planner test, where the two searches meet off the straight line
+        let mut input = generator(
+            "Saaaaaaa\n\
+            aaaaaaaa\n\
+            aaaaaaaa\n\
+            aaaaaaaa\n\
+            aaaaaaaE\n",
+        )
+        .unwrap();
+        input.2.insert(input.1[0], 0);
+        for p in [
+            (0, 3),
+            (1, 3),
+            (2, 3),
+            (3, 3),
+            (4, 6),
+            (3, 6),
+            (2, 6),
+            (1, 6),
+        ] {
+            input.2.insert(p, 5);
+        }
+        assert_eq!(part_1_bidirectional(&input), part_1(&input));
+    }
+
+    #[test]
+    fn test_generator_errors() {
+        assert_eq!(generator("abc\nbcE\n"), Err(ParseError::MissingStart));
+        assert_eq!(generator("Sbc\nbcd\n"), Err(ParseError::MissingGoal));
+        assert_eq!(
+            generator("SbS\nbcE\n"),
+            Err(ParseError::DuplicateStart((0, 0), (0, 2)))
+        );
+        assert_eq!(
+            generator("SbE\nbcE\n"),
+            Err(ParseError::DuplicateGoal((0, 2), (1, 2)))
+        );
+        assert_eq!(
+            generator("Sbc\nb-E\n"),
+            Err(ParseError::UnexpectedChar('-', (1, 1)))
+        );
+        assert_eq!(
+            generator_with_markers("Sbc\nbcE\n", &['S', 'E'], &['E']),
+            Err(ParseError::AmbiguousMarker('E'))
+        );
+    }
+
+    #[test]
+    fn test_multiple_starts() {
+        // every a is a start, so part 1 turns into part 2
+        let input = generator_with_markers(SAMPLE_INPUT, &['S', 'a'], &['E']).unwrap();
+        assert_eq!(input.0.len(), 6);
+        assert_eq!(part_1(&input), 29);
+        assert_eq!(part_1_dijkstras(&input), 29);
+        assert_eq!(part_1_bidirectional(&input), 29);
+        assert_eq!(IncrementalPlanner::new(&input).distance(), Some(29));
+        assert_eq!(shortest_path(&input).unwrap().len(), 29 + 1);
+        let paths = shortest_paths(&input).unwrap();
+        assert_eq!(paths.count(), paths.iter().count().into());
+        assert!(paths.iter().all(|path| input.0.contains(&path[0])));
+    }
+
+    #[test]
+    fn test_multiple_goals() {
+        // two ramps going opposite ways, with a start at the bottom and a goal at the top of each.
+        // non-letter markers sit at the bottom (starts) or top (goals)
+        let input = generator_with_markers(
+            "1bcdefghijklmnopqrstuvwxy2\n\
+            abcdefghijklmnopqrstuvwxyz\n\
+            zyxwvutsrqponmlkjihgfedcba\n\
+            3yxwvutsrqponmlkjihgfedcb4\n",
+            &['1', '4'],
+            &['2', '3'],
+        )
+        .unwrap();
+        assert_eq!(input.2[&(0, 0)], 0);
+        assert_eq!(input.2[&(0, 25)], 25);
+        assert_eq!(part_1(&input), 25);
+        assert_eq!(part_1_dijkstras(&input), 25);
+        assert_eq!(part_1_bidirectional(&input), 25);
+        assert_eq!(part_2(&input), 25);
+        let path = shortest_path(&input).unwrap();
+        assert!(input.0.contains(&path[0]) && input.1.contains(&path[25]));
+        // straight along the top row, and straight along the bottom one
+        let paths = shortest_paths(&input).unwrap();
+        assert_eq!(paths.count(), 2u32.into());
+        let ends = paths
+            .iter()
+            .map(|path| (path[0], path[25]))
+            .collect::<Vec<_>>();
+        assert_eq!(ends, vec![((0, 0), (0, 25)), ((3, 25), (3, 0))]);
+        // walling off 2 leaves 4 to 3
+        let mut planner = IncrementalPlanner::new(&input);
+        assert_eq!(planner.distance(), Some(25));
+        planner.set_height((0, 24), 30);
+        assert_eq!(planner.set_height((1, 25), 30), Some(25));
+        planner.set_height((3, 1), 30);
+        assert_eq!(planner.set_height((2, 0), 30), None);
+    }
 }
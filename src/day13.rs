@@ -1,4 +1,4 @@
-use std::cmp::PartialOrd;
+use std::cmp::Ordering;
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum List {
@@ -6,79 +6,426 @@ pub enum List {
     List(Vec<List>),
 }
 
-// lexical sort, but ints are treated as a single-element list when compared to another list
-impl PartialOrd for List {
-    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+impl List {
+    // the order from the puzzle: lexical sort, but ints are treated as a single-element list when
+    // compared to another list. Nb. this is only a preorder, as 1, [1] and [[1]] are all "equal"
+    pub fn packet_cmp(&self, other: &Self) -> Ordering {
         match (self, other) {
-            (List::Int(a), List::Int(b)) => a.partial_cmp(b),
-            (List::Int(a), List::List(b)) => {
+            (List::Int(a), List::Int(b)) => a.cmp(b),
+            (List::Int(a), List::List(_)) => {
                 let al = List::List(vec![List::Int(*a)]);
-                al.partial_cmp(other)
+                al.packet_cmp(other)
             }
-            (List::List(a), List::Int(b)) => {
+            (List::List(_), List::Int(b)) => {
                 let bl = List::List(vec![List::Int(*b)]);
-                self.partial_cmp(&bl)
+                self.packet_cmp(&bl)
             }
             (List::List(a), List::List(b)) => {
                 for (a, b) in a.iter().zip(b.iter()) {
-                    match a.partial_cmp(b) {
-                        Some(std::cmp::Ordering::Equal) => continue,
-                        Some(order) => return Some(order),
-                        None => return None,
+                    match a.packet_cmp(b) {
+                        Ordering::Equal => continue,
+                        order => return order,
                     }
                 }
-                a.len().partial_cmp(&b.len())
+                a.len().cmp(&b.len())
             }
         }
     }
+
+    // plain structural order: ints before lists, lists lexically. Only equal for equal packets
+    fn structural_cmp(&self, other: &Self) -> Ordering {
+        match (self, other) {
+            (List::Int(a), List::Int(b)) => a.cmp(b),
+            (List::Int(_), List::List(_)) => Ordering::Less,
+            (List::List(_), List::Int(_)) => Ordering::Greater,
+            (List::List(a), List::List(b)) => a
+                .iter()
+                .zip(b.iter())
+                .map(|(a, b)| a.structural_cmp(b))
+                .find(|order| order.is_ne())
+                .unwrap_or_else(|| a.len().cmp(&b.len())),
+        }
+    }
 }
 
-pub type Input = Vec<(List, List)>;
+// total order: the puzzle order first, and packets it considers equal (like 1 and [1]) are
+// ordered by their structure. So sorting gives the same result as the puzzle, and Equal means
+// the same thing as ==
+impl Ord for List {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.packet_cmp(other)
+            .then_with(|| self.structural_cmp(other))
+    }
+}
 
-// parse nested lists of integers, eg: [[1,2],[3,4]]
-fn parse_list(input: &str) -> (List, &str) {
-    let mut input = input.trim();
-    let mut list = Vec::new();
-    while !input.is_empty() {
-        let (value, rest) = if input.starts_with('[') {
-            let (value, rest) = parse_list(&input[1..]);
-            (value, &rest[1..])
-        } else if input.starts_with(']') {
-            break;
-        } else {
-            let (value, rest) = input.split_at(
-                input
-                    .find(|c: char| !c.is_ascii_digit())
-                    .unwrap_or(input.len()),
-            );
-            (List::Int(value.parse().unwrap()), rest)
-        };
-        list.push(value);
-        input = rest.trim_start_matches(',');
+impl PartialOrd for List {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
     }
-    (List::List(list), input)
 }
 
-pub fn generator(input: &str) -> Input {
-    // onput is two lists separated by a newline, then a blank line (repeating)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Side {
+    Left,
+    Right,
+}
 
-    input
-        .split("\n")
-        .collect::<Vec<_>>()
-        .chunks(3)
-        .map(|chunk| {
-            let (list1, _) = parse_list(chunk[0]);
-            let (list2, _) = parse_list(chunk[1]);
-            (list1, list2)
-        })
-        .collect()
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Step {
+    // compared the items at this index on both sides
+    Index(usize),
+    // an int on this side met a list, so it was turned into a single-item list
+    Wrap(Side),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    // two different ints, left and right
+    Ints(i64, i64),
+    // this side ran out of items first
+    RanOut(Side),
+    // nothing decided it, the packets are equal in the puzzle order
+    Same,
+}
+
+// how the puzzle comparison of two packets was decided: the way down to the first comparison
+// that wasn't a tie, and what it was. Displays like the walkthrough in the puzzle, eg.
+// "index 1 → right wrapped in a list → index 0: 2 < 4"
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Explanation {
+    pub steps: Vec<Step>,
+    pub outcome: Outcome,
+}
+
+impl Explanation {
+    pub fn order(&self) -> Ordering {
+        match self.outcome {
+            Outcome::Ints(a, b) => a.cmp(&b),
+            Outcome::RanOut(Side::Left) => Ordering::Less,
+            Outcome::RanOut(Side::Right) => Ordering::Greater,
+            Outcome::Same => Ordering::Equal,
+        }
+    }
+}
+
+impl std::fmt::Display for Side {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Side::Left => write!(f, "left"),
+            Side::Right => write!(f, "right"),
+        }
+    }
+}
+
+impl std::fmt::Display for Explanation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (idx, step) in self.steps.iter().enumerate() {
+            if idx > 0 {
+                write!(f, " → ")?;
+            }
+            match step {
+                Step::Index(i) => write!(f, "index {}", i)?,
+                Step::Wrap(side) => write!(f, "{} wrapped in a list", side)?,
+            }
+        }
+        if !self.steps.is_empty() {
+            write!(f, ": ")?;
+        }
+        match self.outcome {
+            Outcome::Ints(a, b) if a < b => write!(f, "{} < {}", a, b),
+            Outcome::Ints(a, b) => write!(f, "{} > {}", a, b),
+            Outcome::RanOut(side) => write!(f, "{} ran out of items", side),
+            Outcome::Same => write!(f, "packets are the same"),
+        }
+    }
+}
+
+impl List {
+    // the same comparison as packet_cmp, keeping track of how it got to the answer
+    pub fn explain(&self, other: &Self) -> Explanation {
+        let mut steps = vec![];
+        let outcome = self.explain_into(other, &mut steps);
+        Explanation { steps, outcome }
+    }
+
+    fn explain_into(&self, other: &Self, steps: &mut Vec<Step>) -> Outcome {
+        match (self, other) {
+            (List::Int(a), List::Int(b)) if a == b => Outcome::Same,
+            (List::Int(a), List::Int(b)) => Outcome::Ints(*a, *b),
+            (List::List(a), List::List(b)) => {
+                for (idx, (a, b)) in a.iter().zip(b.iter()).enumerate() {
+                    steps.push(Step::Index(idx));
+                    match a.explain_into(b, steps) {
+                        Outcome::Same => steps.pop(),
+                        outcome => return outcome,
+                    };
+                }
+                match a.len().cmp(&b.len()) {
+                    Ordering::Less => Outcome::RanOut(Side::Left),
+                    Ordering::Greater => Outcome::RanOut(Side::Right),
+                    Ordering::Equal => Outcome::Same,
+                }
+            }
+            (List::Int(a), List::List(_)) => {
+                steps.push(Step::Wrap(Side::Left));
+                let outcome = List::List(vec![List::Int(*a)]).explain_into(other, steps);
+                // a wrap that didn't lead anywhere isn't part of the explanation
+                if outcome == Outcome::Same {
+                    steps.pop();
+                }
+                outcome
+            }
+            (List::List(_), List::Int(b)) => {
+                steps.push(Step::Wrap(Side::Right));
+                let outcome = self.explain_into(&List::List(vec![List::Int(*b)]), steps);
+                if outcome == Outcome::Same {
+                    steps.pop();
+                }
+                outcome
+            }
+        }
+    }
+}
+
+// the same format as the input: [1,[2,3]]
+impl std::fmt::Display for List {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            List::Int(n) => write!(f, "{}", n),
+            List::List(items) => {
+                write!(f, "[")?;
+                for (idx, item) in items.iter().enumerate() {
+                    if idx > 0 {
+                        write!(f, ",")?;
+                    }
+                    write!(f, "{}", item)?;
+                }
+                write!(f, "]")
+            }
+        }
+    }
+}
+
+// packets happen to be valid JSON, so they map directly onto JSON arrays and integers
+impl From<&List> for serde_json::Value {
+    fn from(list: &List) -> Self {
+        match list {
+            List::Int(n) => (*n).into(),
+            List::List(items) => items.iter().map(serde_json::Value::from).collect(),
+        }
+    }
+}
+
+// a JSON value that can't be a packet (anything but arrays and integers that fit in an i64)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NotAPacket(pub serde_json::Value);
+
+impl std::fmt::Display for NotAPacket {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "not part of a packet: {}", self.0)
+    }
+}
+
+impl std::error::Error for NotAPacket {}
+
+impl TryFrom<&serde_json::Value> for List {
+    type Error = NotAPacket;
+
+    fn try_from(value: &serde_json::Value) -> Result<Self, Self::Error> {
+        match value {
+            serde_json::Value::Number(n) => n
+                .as_i64()
+                .map(List::Int)
+                .ok_or_else(|| NotAPacket(value.clone())),
+            serde_json::Value::Array(items) => items
+                .iter()
+                .map(List::try_from)
+                .collect::<Result<_, _>>()
+                .map(List::List),
+            _ => Err(NotAPacket(value.clone())),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Input {
+    pub pairs: Vec<(List, List)>,
+    // the same pairs as they were written, for the solutions that work on the text directly
+    pub text: Vec<(String, String)>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    // 1-based line of the input, and 0-based byte offset into that line
+    pub line: usize,
+    pub position: usize,
+    pub expected: &'static str,
+    // None is the end of the line
+    pub found: Option<char>,
+}
+
+impl std::fmt::Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "line {}, position {}: expected {}, found ",
+            self.line, self.position, self.expected
+        )?;
+        match self.found {
+            Some(c) => write!(f, "{:?}", c),
+            None => write!(f, "end of line"),
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+// recursive descent over a single packet:
+//   value := list | int
+//   list  := '[' (value (',' value)*)? ']'
+//   int   := ('-' | '+')? digit+
+// with whitespace allowed between any two tokens
+struct Parser<'a> {
+    input: &'a str,
+    position: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&self) -> Option<char> {
+        self.input[self.position..].chars().next()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(c) = self.peek().filter(|c| c.is_whitespace()) {
+            self.position += c.len_utf8();
+        }
+    }
+
+    fn error(&self, expected: &'static str) -> ParseError {
+        ParseError {
+            line: 1,
+            position: self.position,
+            expected,
+            found: self.peek(),
+        }
+    }
+
+    fn expect(&mut self, c: char, expected: &'static str) -> Result<(), ParseError> {
+        self.skip_whitespace();
+        if self.peek() != Some(c) {
+            return Err(self.error(expected));
+        }
+        self.position += 1;
+        Ok(())
+    }
+
+    fn value(&mut self) -> Result<List, ParseError> {
+        self.skip_whitespace();
+        match self.peek() {
+            Some('[') => self.list(),
+            Some('-' | '+' | '0'..='9') => self.int(),
+            _ => Err(self.error("'[' or a number")),
+        }
+    }
+
+    fn list(&mut self) -> Result<List, ParseError> {
+        self.expect('[', "'['")?;
+        let mut items = vec![];
+        self.skip_whitespace();
+        if self.peek() == Some(']') {
+            self.position += 1;
+            return Ok(List::List(items));
+        }
+        loop {
+            items.push(self.value()?);
+            self.skip_whitespace();
+            match self.peek() {
+                Some(',') => self.position += 1,
+                Some(']') => {
+                    self.position += 1;
+                    return Ok(List::List(items));
+                }
+                _ => return Err(self.error("',' or ']'")),
+            }
+        }
+    }
+
+    fn int(&mut self) -> Result<List, ParseError> {
+        let start = self.position;
+        if let Some('-' | '+') = self.peek() {
+            self.position += 1;
+        }
+        let digits = self.position;
+        while let Some('0'..='9') = self.peek() {
+            self.position += 1;
+        }
+        if self.position == digits {
+            return Err(self.error("a digit"));
+        }
+        self.input[start..self.position]
+            .parse()
+            .map(List::Int)
+            .map_err(|_| {
+                self.position = start;
+                self.error("a number that fits in 64 bits")
+            })
+    }
+}
+
+// parse a single packet, eg: [[1,2],[3,-4]]. Nothing but whitespace may follow it
+pub fn parse_list(input: &str) -> Result<List, ParseError> {
+    let mut parser = Parser { input, position: 0 };
+    let list = parser.value()?;
+    parser.skip_whitespace();
+    if parser.peek().is_some() {
+        return Err(parser.error("end of line"));
+    }
+    Ok(list)
+}
+
+pub fn generator(input: &str) -> Result<Input, ParseError> {
+    // input is pairs of packets, one per line, with the pairs separated by blank lines. Any
+    // number of blank lines will do, but each pair has to be exactly two packets, so a missing
+    // one is reported where it's missing rather than throwing off every pair after it
+    let lines = input.lines().enumerate().collect::<Vec<_>>();
+    let mut pairs = vec![];
+    let mut text = vec![];
+    for block in lines
+        .split(|(_, line)| line.trim().is_empty())
+        .filter(|block| !block.is_empty())
+    {
+        let mut packets = block
+            .iter()
+            .map(|&(idx, line)| {
+                parse_list(line)
+                    .map(|list| (list, line.trim().to_string()))
+                    .map_err(|err| ParseError {
+                        line: idx + 1,
+                        ..err
+                    })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        if packets.len() != 2 {
+            return Err(ParseError {
+                line: block[0].0 + 1,
+                position: 0,
+                expected: "a pair of packets, one per line",
+                found: None,
+            });
+        }
+        let (b, b_text) = packets.pop().unwrap();
+        let (a, a_text) = packets.pop().unwrap();
+        pairs.push((a, b));
+        text.push((a_text, b_text));
+    }
+    Ok(Input { pairs, text })
 }
 
 pub fn part_1(input: &Input) -> i64 {
     // sum of the indices of pairs that are in order
     let mut sum = 0;
-    for (i, (a, b)) in input.iter().enumerate() {
-        if a < b {
+    for (i, (a, b)) in input.pairs.iter().enumerate() {
+        if a.packet_cmp(b).is_lt() {
             sum += (i + 1) as i64;
             // debug: sample should have 1, 2, 4, and 6
             // println!("{:?} < {:?} at index {}", a, b, i + 1)
@@ -87,27 +434,887 @@ pub fn part_1(input: &Input) -> i64 {
     sum
 }
 
-pub fn part_2(input: &Input) -> i64 {
-    let marker1 = parse_list("[[2]]").0;
-    let marker2 = parse_list("[[6]]").0;
-    // first combine all the pairs to one list
-    let mut list = vec![marker1.clone(), marker2.clone()];
-    for (a, b) in input {
-        list.push(a.clone());
-        list.push(b.clone());
+// compare two packets straight from their text, without building them first. The only tricky
+// bit is an int meeting a list: we pretend the int was written as [int] by playing it back
+// followed by an extra ']' on that side. None if either side turns out not to be a packet
+// before the order is decided (only the tokens are checked, not that the brackets balance)
+pub fn compare_packets(left: &str, right: &str) -> Option<Ordering> {
+    let mut left = Tokens::new(left);
+    let mut right = Tokens::new(right);
+    loop {
+        let order = match (left.next(), right.next()) {
+            (Some(Token::Invalid), _) | (_, Some(Token::Invalid)) => return None,
+            (Some(Token::Int(a)), Some(Token::Int(b))) if a != b => a.cmp(&b),
+            (Some(Token::Int(_)), Some(Token::Int(_))) => continue,
+            (Some(Token::Open), Some(Token::Open)) | (Some(Token::Close), Some(Token::Close)) => {
+                continue
+            }
+            // one side ran out of items
+            (Some(Token::Close), _) => Ordering::Less,
+            (_, Some(Token::Close)) => Ordering::Greater,
+            (Some(Token::Int(a)), Some(Token::Open)) => {
+                left.wrap(a);
+                continue;
+            }
+            (Some(Token::Open), Some(Token::Int(b))) => {
+                right.wrap(b);
+                continue;
+            }
+            (a, b) => a.is_some().cmp(&b.is_some()),
+        };
+        return Some(order);
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Token {
+    Open,
+    Close,
+    Int(i64),
+    // anything that can't start a token, or an int that doesn't fit in 64 bits. Always last
+    Invalid,
+}
+
+// tokens of a single packet, skipping commas and whitespace
+struct Tokens<'a> {
+    bytes: &'a [u8],
+    position: usize,
+    // an int to play back before carrying on, and how many made up ']' follow it
+    replay: Option<i64>,
+    closing: usize,
+}
+
+impl<'a> Tokens<'a> {
+    fn new(packet: &'a str) -> Self {
+        Tokens {
+            bytes: packet.as_bytes(),
+            position: 0,
+            replay: None,
+            closing: 0,
+        }
+    }
+
+    // negative numbers are built up negatively, so i64::MIN fits
+    fn int(&mut self) -> Token {
+        let negative = self.bytes[self.position] == b'-';
+        if let b'-' | b'+' = self.bytes[self.position] {
+            self.position += 1;
+        }
+        let digits = self.position;
+        let mut value: i64 = 0;
+        while let Some(digit @ b'0'..=b'9') = self.bytes.get(self.position) {
+            let digit = (digit - b'0') as i64;
+            let next = value.checked_mul(10).and_then(|value| {
+                if negative {
+                    value.checked_sub(digit)
+                } else {
+                    value.checked_add(digit)
+                }
+            });
+            match next {
+                Some(next) => value = next,
+                None => return Token::Invalid,
+            }
+            self.position += 1;
+        }
+        if self.position == digits {
+            return Token::Invalid;
+        }
+        Token::Int(value)
+    }
+
+    // the int we just read actually started a list (that the other side already opened)
+    fn wrap(&mut self, value: i64) {
+        self.replay = Some(value);
+        self.closing += 1;
+    }
+}
+
+impl Iterator for Tokens<'_> {
+    type Item = Token;
+
+    fn next(&mut self) -> Option<Token> {
+        if let Some(value) = self.replay.take() {
+            return Some(Token::Int(value));
+        }
+        if self.closing > 0 {
+            self.closing -= 1;
+            return Some(Token::Close);
+        }
+        while let Some(b',' | b' ' | b'\t' | b'\r' | b'\n') = self.bytes.get(self.position) {
+            self.position += 1;
+        }
+        let token = match self.bytes.get(self.position)? {
+            b'[' => Token::Open,
+            b']' => Token::Close,
+            _ => {
+                let token = self.int();
+                if token == Token::Invalid {
+                    // stop here, so a bad packet can't keep us going forever
+                    self.position = self.bytes.len();
+                }
+                return Some(token);
+            }
+        };
+        self.position += 1;
+        Some(token)
+    }
+}
+
+// part 1 without parsing into lists at all
+pub fn part_1_streaming(input: &Input) -> i64 {
+    input
+        .text
+        .iter()
+        .enumerate()
+        .filter(|(_, (a, b))| compare_packets(a, b) == Some(Ordering::Less))
+        .map(|(i, _)| (i + 1) as i64)
+        .sum()
+}
+
+// all packets flattened into one buffer, in pre-order: a list is followed by its items, and
+// remembers where it ends so we can skip over it. Packets are referred to by the order they
+// were pushed in, so they can be compared and sorted without moving (or cloning) any of them
+#[derive(Debug, Clone, Default)]
+pub struct Arena {
+    nodes: Vec<ArenaNode>,
+    // where each packet starts in `nodes`
+    roots: Vec<usize>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ArenaNode {
+    Int(i64),
+    // index just past the last item
+    List { end: usize },
+}
+
+impl Arena {
+    // add a packet, returning its index
+    pub fn push(&mut self, list: &List) -> usize {
+        self.roots.push(self.nodes.len());
+        self.push_node(list);
+        self.roots.len() - 1
+    }
+
+    // add a packet straight from its text, without building a List first. None (and nothing
+    // added) if it isn't a packet, though like compare_packets, only the tokens and brackets
+    // are checked
+    pub fn push_text(&mut self, packet: &str) -> Option<usize> {
+        let start = self.nodes.len();
+        let mut tokens = Tokens::new(packet);
+        // lists we're in the middle of
+        let mut open = vec![];
+        let complete = loop {
+            match tokens.next() {
+                Some(Token::Open) => {
+                    open.push(self.nodes.len());
+                    self.nodes.push(ArenaNode::List { end: 0 });
+                }
+                Some(Token::Close) => match open.pop() {
+                    Some(node) => {
+                        self.nodes[node] = ArenaNode::List {
+                            end: self.nodes.len(),
+                        }
+                    }
+                    None => break false,
+                },
+                Some(Token::Int(n)) => self.nodes.push(ArenaNode::Int(n)),
+                Some(Token::Invalid) | None => break false,
+            }
+            if open.is_empty() {
+                // that's the whole packet, and there shouldn't be anything after it
+                break tokens.next().is_none();
+            }
+        };
+        if !complete {
+            self.nodes.truncate(start);
+            return None;
+        }
+        self.roots.push(start);
+        Some(self.roots.len() - 1)
+    }
+
+    fn push_node(&mut self, list: &List) {
+        match list {
+            List::Int(n) => self.nodes.push(ArenaNode::Int(*n)),
+            List::List(items) => {
+                let node = self.nodes.len();
+                self.nodes.push(ArenaNode::List { end: 0 });
+                for item in items {
+                    self.push_node(item);
+                }
+                self.nodes[node] = ArenaNode::List {
+                    end: self.nodes.len(),
+                };
+            }
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.roots.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.roots.is_empty()
+    }
+
+    // compare two packets by index, in the puzzle order (see List::packet_cmp)
+    pub fn cmp(&self, a: usize, b: usize) -> Ordering {
+        self.cmp_nodes(self.roots[a], self.roots[b])
+    }
+
+    // packet indices in sorted order. The sort is stable, so packets the puzzle order considers
+    // equal stay in the order they were pushed
+    pub fn sorted(&self) -> Vec<usize> {
+        let mut order = (0..self.len()).collect::<Vec<_>>();
+        order.sort_by(|&a, &b| self.cmp(a, b));
+        order
+    }
+
+    pub fn display(&self, packet: usize) -> impl std::fmt::Display + '_ {
+        ArenaPacket(self, self.roots[packet])
+    }
+
+    // the nodes of the items directly in the list at `node`
+    fn items(&self, node: usize) -> impl Iterator<Item = usize> + '_ {
+        let end = match self.nodes[node] {
+            ArenaNode::List { end } => end,
+            ArenaNode::Int(_) => node + 1,
+        };
+        let first = Some(node + 1).filter(|&item| item < end);
+        std::iter::successors(first, move |&item| {
+            let next = match self.nodes[item] {
+                ArenaNode::Int(_) => item + 1,
+                ArenaNode::List { end } => end,
+            };
+            Some(next).filter(|&next| next < end)
+        })
+    }
+
+    fn cmp_nodes(&self, a: usize, b: usize) -> Ordering {
+        match (self.nodes[a], self.nodes[b]) {
+            (ArenaNode::Int(x), ArenaNode::Int(y)) => x.cmp(&y),
+            (ArenaNode::Int(_), ArenaNode::List { .. }) => self.cmp_int_list(a, b),
+            (ArenaNode::List { .. }, ArenaNode::Int(_)) => self.cmp_int_list(b, a).reverse(),
+            (ArenaNode::List { .. }, ArenaNode::List { .. }) => {
+                let (mut a, mut b) = (self.items(a), self.items(b));
+                loop {
+                    match (a.next(), b.next()) {
+                        (Some(x), Some(y)) => match self.cmp_nodes(x, y) {
+                            Ordering::Equal => continue,
+                            order => return order,
+                        },
+                        (x, y) => return x.is_some().cmp(&y.is_some()),
+                    }
+                }
+            }
+        }
+    }
+
+    // an int against a list is [int] against the list: compare with the first item, and if
+    // that's a tie, the list wins if it has anything left
+    fn cmp_int_list(&self, int: usize, list: usize) -> Ordering {
+        let mut items = self.items(list);
+        match items.next() {
+            None => Ordering::Greater,
+            Some(first) => self.cmp_nodes(int, first).then(if items.next().is_some() {
+                Ordering::Less
+            } else {
+                Ordering::Equal
+            }),
+        }
+    }
+}
+
+struct ArenaPacket<'a>(&'a Arena, usize);
+
+impl std::fmt::Display for ArenaPacket<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let ArenaPacket(arena, node) = *self;
+        match arena.nodes[node] {
+            ArenaNode::Int(n) => write!(f, "{}", n),
+            ArenaNode::List { .. } => {
+                write!(f, "[")?;
+                for (idx, item) in arena.items(node).enumerate() {
+                    if idx > 0 {
+                        write!(f, ",")?;
+                    }
+                    write!(f, "{}", ArenaPacket(arena, item))?;
+                }
+                write!(f, "]")
+            }
+        }
+    }
+}
+
+// divider packets, separated by ';'. Part 2 uses "[[2]];[[6]]", unless DAY13_DIVIDERS says
+// otherwise. Errors report the 1-based index of the divider as the line
+pub fn parse_dividers(dividers: &str) -> Result<Vec<List>, ParseError> {
+    dividers
+        .split(';')
+        .enumerate()
+        .map(|(idx, divider)| {
+            parse_list(divider).map_err(|err| ParseError {
+                line: idx + 1,
+                ..err
+            })
+        })
+        .collect()
+}
+
+fn dividers() -> Result<Vec<List>, ParseError> {
+    let dividers = std::env::var("DAY13_DIVIDERS").unwrap_or_else(|_| "[[2]];[[6]]".to_string());
+    parse_dividers(&dividers)
+}
+
+// 1-based position each divider ends up at when sorted in with all the input packets (and the
+// other dividers), in the puzzle order. That's one more than the number of packets that sort
+// before it, so there's no need to sort anything: one pass over the input per divider. Ties
+// go the way a stable sort with the dividers up front would: dividers before input packets,
+// and in the order they were given among themselves
+pub fn divider_ranks(input: &Input, dividers: &[List]) -> Vec<usize> {
+    dividers
+        .iter()
+        .enumerate()
+        .map(|(idx, divider)| {
+            let packets = input.pairs.iter().flat_map(|(a, b)| [a, b]);
+            let before = packets
+                .filter(|packet| packet.packet_cmp(divider).is_lt())
+                .count();
+            let dividers_before = dividers
+                .iter()
+                .enumerate()
+                .filter(|(other, packet)| match packet.packet_cmp(divider) {
+                    Ordering::Less => true,
+                    Ordering::Equal => *other < idx,
+                    Ordering::Greater => false,
+                })
+                .count();
+            1 + before + dividers_before
+        })
+        .collect()
+}
+
+pub fn part_2(input: &Input) -> Result<i64, ParseError> {
+    let dividers = dividers()?;
+    Ok(divider_ranks(input, &dividers)
+        .into_iter()
+        .map(|rank| rank as i64)
+        .product())
+}
+
+// the same ranks, by sorting everything the way part 2 used to. Everything goes in one arena,
+// and we sort indices into it rather than the packets themselves. The input packets go in
+// straight from their text, so none of them get copied
+pub fn sorted_divider_ranks(input: &Input, dividers: &[List]) -> Vec<usize> {
+    let mut arena = Arena::default();
+    let dividers = dividers
+        .iter()
+        .map(|divider| arena.push(divider))
+        .collect::<Vec<_>>();
+    for packet in input.text.iter().flat_map(|(a, b)| [a, b]) {
+        arena
+            .push_text(packet)
+            .expect("the generator already checked the packets");
     }
-    // sort the list
-    list.sort_by(|a, b| a.partial_cmp(b).unwrap());
-    // find the indices of [[2]] and [[6]]
-    let mut prod = 1;
-    for (i, item) in list.iter().enumerate() {
-        println!("{:?} ", item);
-        if item == &marker1 {
-            prod *= (i + 1) as i64;
+    let mut ranks = vec![0; dividers.len()];
+    for (i, packet) in arena.sorted().into_iter().enumerate() {
+        if let Some(divider) = dividers.iter().position(|&divider| divider == packet) {
+            ranks[divider] = i + 1;
         }
-        if item == &marker2 {
-            prod *= (i + 1) as i64;
+    }
+    ranks
+}
+
+pub fn part_2_arena(input: &Input) -> Result<i64, ParseError> {
+    let dividers = dividers()?;
+    Ok(sorted_divider_ranks(input, &dividers)
+        .into_iter()
+        .map(|rank| rank as i64)
+        .product())
+}
+
+// random (valid) packets, for fuzzing the parser and the comparisons. Every packet is a list
+// of up to `max_width` items, and every item is either an int in `values`, or (if we're not
+// `max_depth` lists deep yet) another list, with even odds
+#[derive(Debug, Clone)]
+pub struct RandomPackets {
+    pub max_depth: u32,
+    pub max_width: u32,
+    pub values: std::ops::RangeInclusive<i64>,
+}
+
+impl Default for RandomPackets {
+    fn default() -> Self {
+        RandomPackets {
+            max_depth: 4,
+            max_width: 5,
+            values: 0..=10,
+        }
+    }
+}
+
+impl RandomPackets {
+    pub fn packet(&self, rng: &mut oorandom::Rand64) -> List {
+        self.list(rng, 1)
+    }
+
+    fn list(&self, rng: &mut oorandom::Rand64, depth: u32) -> List {
+        let width = rng.rand_range(0..self.max_width as u64 + 1);
+        List::List((0..width).map(|_| self.item(rng, depth)).collect())
+    }
+
+    fn item(&self, rng: &mut oorandom::Rand64, depth: u32) -> List {
+        if depth < self.max_depth && rng.rand_range(0..2) == 0 {
+            return self.list(rng, depth + 1);
+        }
+        let (start, end) = (*self.values.start(), *self.values.end());
+        // the span only overflows for the full i64 range, where any u64 will do
+        let span = (end.wrapping_sub(start) as u64).wrapping_add(1);
+        let offset = if span == 0 {
+            rng.rand_u64()
+        } else {
+            rng.rand_range(0..span)
+        };
+        List::Int(start.wrapping_add(offset as i64))
+    }
+
+    // `count` pairs in the puzzle input format. The same seed always gives the same input
+    pub fn input(&self, seed: u64, count: usize) -> String {
+        let mut rng = oorandom::Rand64::new(seed as u128);
+        (0..count)
+            .map(|_| format!("{}\n{}\n", self.packet(&mut rng), self.packet(&mut rng)))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE_INPUT: &str = "[1,1,3,1,1]
+[1,1,5,1,1]
+
+[[1],[2,3,4]]
+[[1],4]
+
+[9]
+[[8,7,6]]
+
+[[4,4],4,4]
+[[4,4],4,4,4]
+
+[7,7,7,7]
+[7,7,7]
+
+[]
+[3]
+
+[[[]]]
+[[]]
+
+[1,[2,[3,[4,[5,6,7]]]],8,9]
+[1,[2,[3,[4,[5,6,0]]]],8,9]
+";
+
+    #[test]
+    fn test_parse_list() {
+        use List::{Int, List as L};
+        assert_eq!(
+            parse_list("[[1],[2,3,4]]"),
+            Ok(L(vec![L(vec![Int(1)]), L(vec![Int(2), Int(3), Int(4)])]))
+        );
+        assert_eq!(parse_list("[]"), Ok(L(vec![])));
+        assert_eq!(parse_list("[[[]]]"), Ok(L(vec![L(vec![L(vec![])])])));
+        assert_eq!(
+            parse_list(" [ -1, +2 ,[ 10 ] ] \r"),
+            Ok(L(vec![Int(-1), Int(2), L(vec![Int(10)])]))
+        );
+        assert_eq!(parse_list("7"), Ok(Int(7)));
+    }
+
+    #[test]
+    fn test_parse_list_errors() {
+        let err = |position, expected, found| ParseError {
+            line: 1,
+            position,
+            expected,
+            found,
+        };
+        assert_eq!(parse_list("[1,2"), Err(err(4, "',' or ']'", None)));
+        assert_eq!(parse_list("[1,2]]"), Err(err(5, "end of line", Some(']'))));
+        assert_eq!(
+            parse_list("[1,,2]"),
+            Err(err(3, "'[' or a number", Some(',')))
+        );
+        assert_eq!(
+            parse_list("[1,x]"),
+            Err(err(3, "'[' or a number", Some('x')))
+        );
+        assert_eq!(parse_list("[1 2]"), Err(err(3, "',' or ']'", Some('2'))));
+        assert_eq!(parse_list("[-]"), Err(err(2, "a digit", Some(']'))));
+        assert_eq!(parse_list(""), Err(err(0, "'[' or a number", None)));
+        assert_eq!(
+            parse_list("[99999999999999999999]"),
+            Err(err(1, "a number that fits in 64 bits", Some('9')))
+        );
+    }
+
+    #[test]
+    fn test_generator() {
+        let input = generator(SAMPLE_INPUT).unwrap();
+        assert_eq!(input.pairs.len(), 8);
+        assert_eq!(input.text[6], ("[[[]]]".to_string(), "[[]]".to_string()));
+        assert_eq!(
+            input.pairs[5],
+            (parse_list("[]").unwrap(), parse_list("[3]").unwrap())
+        );
+        // windows line endings and extra blank lines make no difference
+        let messy = SAMPLE_INPUT.replace("\n\n", "\n\n\n").replace('\n', "\r\n");
+        let messy = format!("\r\n{}\r\n\r\n", messy);
+        assert_eq!(generator(&messy), Ok(input));
+    }
+
+    #[test]
+    fn test_generator_errors() {
+        assert_eq!(
+            generator("[1]\n[2]\n\n[3]\n[4,]\n"),
+            Err(ParseError {
+                line: 5,
+                position: 3,
+                expected: "'[' or a number",
+                found: Some(']')
+            })
+        );
+        let missing = ParseError {
+            line: 4,
+            position: 0,
+            expected: "a pair of packets, one per line",
+            found: None,
+        };
+        assert_eq!(generator("[1]\n[2]\n\n[3]\n"), Err(missing.clone()));
+        // rather than pairing [3] with [4] and [5] with [6]
+        assert_eq!(
+            generator("[1]\n[2]\n\n[3]\n\n[4]\n[5]\n\n[6]\n"),
+            Err(missing.clone())
+        );
+        assert_eq!(generator("[1]\n[2]\n\n[3]\n[4]\n[5]\n"), Err(missing));
+    }
+
+    #[test]
+    fn test_part_1() {
+        assert_eq!(part_1(&generator(SAMPLE_INPUT).unwrap()), 13);
+    }
+
+    #[test]
+    fn test_part_2() {
+        let input = generator(SAMPLE_INPUT).unwrap();
+        let dividers = parse_dividers("[[2]];[[6]]").unwrap();
+        assert_eq!(divider_ranks(&input, &dividers), vec![10, 14]);
+        assert_eq!(sorted_divider_ranks(&input, &dividers), vec![10, 14]);
+    }
+
+    #[test]
+    fn test_display() {
+        let packets = SAMPLE_INPUT.lines().filter(|line| !line.is_empty());
+        for line in packets {
+            let list = parse_list(line).unwrap();
+            assert_eq!(list.to_string(), line);
+            assert_eq!(parse_list(&list.to_string()), Ok(list));
+        }
+        // whitespace and explicit signs don't survive
+        let list = parse_list("[ +1, [-2 ] ]").unwrap();
+        assert_eq!(list.to_string(), "[1,[-2]]");
+        assert_eq!(parse_list(&list.to_string()), Ok(list));
+    }
+
+    #[test]
+    fn test_json() {
+        let packets = SAMPLE_INPUT.lines().filter(|line| !line.is_empty());
+        for line in packets {
+            let list = parse_list(line).unwrap();
+            let json: serde_json::Value = serde_json::from_str(line).unwrap();
+            assert_eq!(serde_json::Value::from(&list), json);
+            assert_eq!(List::try_from(&json), Ok(list.clone()));
+            assert_eq!(json.to_string(), list.to_string());
+        }
+        for bad in [
+            "[1,2.5]",
+            "[\"1\"]",
+            "{}",
+            "[null]",
+            "[18446744073709551615]",
+        ] {
+            let json: serde_json::Value = serde_json::from_str(bad).unwrap();
+            assert!(List::try_from(&json).is_err(), "{}", bad);
+        }
+        assert_eq!(
+            List::try_from(&serde_json::json!([1, [true]])),
+            Err(NotAPacket(serde_json::json!(true)))
+        );
+    }
+
+    // random packets with small numbers and short lists, so that there are plenty of ties and
+    // int-vs-list comparisons
+    fn random_packets(seed: u64, count: usize) -> Vec<List> {
+        let packets = RandomPackets {
+            max_depth: 4,
+            max_width: 3,
+            values: 0..=3,
+        };
+        let mut rng = oorandom::Rand64::new(seed as u128);
+        (0..count).map(|_| packets.packet(&mut rng)).collect()
+    }
+
+    #[test]
+    fn test_ord_is_reflexive_and_antisymmetric() {
+        let packets = random_packets(13, 300);
+        for a in packets.iter() {
+            assert_eq!(a.cmp(a), Ordering::Equal, "{}", a);
+            for b in packets.iter() {
+                assert_eq!(a.cmp(b), b.cmp(a).reverse(), "{} {}", a, b);
+                assert_eq!(a.cmp(b).is_eq(), a == b, "{} {}", a, b);
+                assert_eq!(a.packet_cmp(b), b.packet_cmp(a).reverse(), "{} {}", a, b);
+            }
+        }
+    }
+
+    #[test]
+    fn test_ord_is_transitive() {
+        let packets = random_packets(1313, 60);
+        for a in packets.iter() {
+            for b in packets.iter() {
+                for c in packets.iter() {
+                    if a <= b && b <= c {
+                        assert!(a <= c, "{} {} {}", a, b, c);
+                    }
+                    // the puzzle order on its own is a preorder, which sorting relies on too
+                    if a.packet_cmp(b).is_le() && b.packet_cmp(c).is_le() {
+                        assert!(a.packet_cmp(c).is_le(), "{} {} {}", a, b, c);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_ord_agrees_with_puzzle_order() {
+        let packets = random_packets(131313, 300);
+        let mut sorted = packets.clone();
+        sorted.sort();
+        for (i, a) in sorted.iter().enumerate() {
+            for b in sorted[i..].iter() {
+                assert!(a.packet_cmp(b).is_le(), "{} {}", a, b);
+            }
+        }
+        let one = parse_list("1").unwrap();
+        let wrapped = parse_list("[[1]]").unwrap();
+        assert_eq!(one.packet_cmp(&wrapped), Ordering::Equal);
+        assert_ne!(one, wrapped);
+        assert_eq!(one.cmp(&wrapped), Ordering::Less);
+    }
+
+    #[test]
+    fn test_part_1_streaming() {
+        assert_eq!(part_1_streaming(&generator(SAMPLE_INPUT).unwrap()), 13);
+    }
+
+    #[test]
+    fn test_compare_packets() {
+        let packets = random_packets(1234, 200);
+        for a in packets.iter() {
+            for b in packets.iter() {
+                let (a_text, b_text) = (a.to_string(), b.to_string());
+                assert_eq!(
+                    compare_packets(&a_text, &b_text),
+                    Some(a.packet_cmp(b)),
+                    "{} {}",
+                    a_text,
+                    b_text
+                );
+            }
+        }
+        assert_eq!(
+            compare_packets("[ -3, [4]]", "[[-3],4]"),
+            Some(Ordering::Equal)
+        );
+        assert_eq!(compare_packets("[[[-10]]]", "[-9]"), Some(Ordering::Less));
+        assert_eq!(
+            compare_packets("[[1],[2,3]]", "[1,[2]]"),
+            Some(Ordering::Greater)
+        );
+        assert_eq!(
+            compare_packets("[-9223372036854775808]", "[9223372036854775807]"),
+            Some(Ordering::Less)
+        );
+        assert_eq!(compare_packets("[x]", "[x]"), None);
+        assert_eq!(compare_packets("[1,-]", "[1,2]"), None);
+        assert_eq!(compare_packets("[9223372036854775808]", "[1]"), None);
+        // decided before the bad bit
+        assert_eq!(compare_packets("[1,x]", "[2]"), Some(Ordering::Less));
+    }
+
+    #[test]
+    fn test_arena() {
+        let packets = random_packets(4321, 200);
+        let mut arena = Arena::default();
+        for (idx, packet) in packets.iter().enumerate() {
+            assert_eq!(arena.push(packet), idx);
+            assert_eq!(arena.display(idx).to_string(), packet.to_string());
+        }
+        assert_eq!(arena.len(), packets.len());
+        for (a, x) in packets.iter().enumerate() {
+            for (b, y) in packets.iter().enumerate() {
+                assert_eq!(arena.cmp(a, b), x.packet_cmp(y), "{} {}", x, y);
+            }
+        }
+        let sorted = arena.sorted();
+        for pair in sorted.windows(2) {
+            assert!(packets[pair[0]].packet_cmp(&packets[pair[1]]).is_le());
+        }
+
+        // the same nodes straight from the text
+        let mut from_text = Arena::default();
+        for (idx, packet) in packets.iter().enumerate() {
+            assert_eq!(from_text.push_text(&packet.to_string()), Some(idx));
+        }
+        assert_eq!(from_text.nodes, arena.nodes);
+        assert_eq!(from_text.roots, arena.roots);
+        for bad in ["[1,[2]", "[1]]", "[1][2]", "[x]", "", "[1,-]"] {
+            assert_eq!(from_text.push_text(bad), None, "{:?}", bad);
+        }
+        assert_eq!(from_text.len(), packets.len());
+        assert_eq!(from_text.nodes, arena.nodes);
+        assert_eq!(from_text.push_text(" 7 "), Some(packets.len()));
+    }
+
+    #[test]
+    fn test_explain() {
+        let expected = [
+            "index 2: 3 < 5",
+            "index 1 → right wrapped in a list → index 0: 2 < 4",
+            "index 0 → left wrapped in a list → index 0: 9 > 8",
+            "left ran out of items",
+            "right ran out of items",
+            "left ran out of items",
+            "index 0: right ran out of items",
+            "index 1 → index 1 → index 1 → index 1 → index 2: 7 > 0",
+        ];
+        let input = generator(SAMPLE_INPUT).unwrap();
+        for ((a, b), expected) in input.pairs.iter().zip(expected) {
+            assert_eq!(a.explain(b).to_string(), expected);
+        }
+        let explanation = input.pairs[1].0.explain(&input.pairs[1].1);
+        assert_eq!(
+            explanation,
+            Explanation {
+                steps: vec![Step::Index(1), Step::Wrap(Side::Right), Step::Index(0)],
+                outcome: Outcome::Ints(2, 4),
+            }
+        );
+        let one = parse_list("[1]").unwrap();
+        let wrapped = parse_list("[[[1]]]").unwrap();
+        assert_eq!(one.explain(&wrapped).to_string(), "packets are the same");
+    }
+
+    #[test]
+    fn test_explain_agrees_with_packet_cmp() {
+        let packets = random_packets(2468, 200);
+        for a in packets.iter() {
+            for b in packets.iter() {
+                assert_eq!(a.explain(b).order(), a.packet_cmp(b), "{} {}", a, b);
+            }
+        }
+    }
+
+    #[test]
+    fn test_divider_ranks() {
+        let input = generator(SAMPLE_INPUT).unwrap();
+        let dividers = parse_dividers("[[2]];[[6]]").unwrap();
+        assert_eq!(divider_ranks(&input, &dividers), vec![10, 14]);
+        // in front of everything, after everything, and tied with [1,1,3,1,1] in the puzzle order
+        // (and each other), so they go before it in the order they're given
+        let dividers = parse_dividers("[]; [[[[99]]]] ;[[1],1,3,1,1];[1,[1],3,1,1]").unwrap();
+        assert_eq!(divider_ranks(&input, &dividers), vec![1, 20, 5, 6]);
+        assert_eq!(sorted_divider_ranks(&input, &dividers), vec![1, 20, 5, 6]);
+        // same as a stable sort of everything, dividers first
+        let mut packets = dividers
+            .iter()
+            .cloned()
+            .chain(input.pairs.iter().flat_map(|(a, b)| [a.clone(), b.clone()]))
+            .collect::<Vec<_>>();
+        packets.sort_by(|a, b| a.packet_cmp(b));
+        for (divider, rank) in dividers.iter().zip(divider_ranks(&input, &dividers)) {
+            assert_eq!(&packets[rank - 1], divider);
+        }
+        // a tie with the default dividers: [2] is [[2]] in the puzzle order
+        let input = generator("[2]\n[7]\n").unwrap();
+        let dividers = parse_dividers("[[2]];[[6]]").unwrap();
+        assert_eq!(divider_ranks(&input, &dividers), vec![1, 3]);
+        assert_eq!(sorted_divider_ranks(&input, &dividers), vec![1, 3]);
+    }
+
+    #[test]
+    fn test_parse_dividers() {
+        assert_eq!(
+            parse_dividers("[[2]];[[6]"),
+            Err(ParseError {
+                line: 2,
+                position: 4,
+                expected: "',' or ']'",
+                found: None
+            })
+        );
+    }
+
+    #[test]
+    fn test_random_packets() {
+        let settings = [
+            RandomPackets::default(),
+            RandomPackets {
+                max_depth: 1,
+                max_width: 20,
+                values: -5..=5,
+            },
+            RandomPackets {
+                max_depth: 8,
+                max_width: 2,
+                values: i64::MIN..=i64::MAX,
+            },
+            RandomPackets {
+                max_depth: 3,
+                max_width: 0,
+                values: 0..=0,
+            },
+        ];
+        for (seed, packets) in settings.iter().enumerate() {
+            let text = packets.input(seed as u64, 100);
+            assert_eq!(text, packets.input(seed as u64, 100), "not deterministic");
+            let input = generator(&text).unwrap();
+            assert_eq!(input.pairs.len(), 100);
+            for ((a, b), (a_text, b_text)) in input.pairs.iter().zip(input.text.iter()) {
+                for (packet, packet_text) in [(a, a_text), (b, b_text)] {
+                    assert_eq!(&packet.to_string(), packet_text);
+                    assert_eq!(parse_list(&packet.to_string()).as_ref(), Ok(packet));
+                    assert!(depth(packet) <= packets.max_depth, "{}", packet);
+                }
+                assert_eq!(compare_packets(a_text, b_text), Some(a.packet_cmp(b)));
+            }
+            assert_eq!(part_1(&input), part_1_streaming(&input));
+            let dividers = parse_dividers("[[2]];[[6]];[1];[]").unwrap();
+            assert_eq!(
+                divider_ranks(&input, &dividers),
+                sorted_divider_ranks(&input, &dividers)
+            );
+        }
+    }
+
+    fn depth(packet: &List) -> u32 {
+        match packet {
+            List::Int(_) => 0,
+            List::List(items) => 1 + items.iter().map(depth).max().unwrap_or(0),
         }
     }
-    prod
 }
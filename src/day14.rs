@@ -1,225 +1,1105 @@
+use std::cell::OnceCell;
 use std::collections::VecDeque;
+use std::fmt;
 
-type Grid = Vec<Vec<bool>>;
+// sand pours in from here, unless the input (or DAY14_SOURCES) says otherwise
+const SOURCE: (i64, usize) = (500, 0);
 
-const GRID_SIZE: usize = 1024; // TODO: make this dynamic?
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Cell {
+    Air,
+    Rock,
+    Sand,
+    Source,
+    // water that's filled a basin
+    Water,
+    // water on its way somewhere else
+    Flowing,
+}
 
-// input is a list of lines in the format x,y -> x,y [-> x,y]*
-pub fn generator(input: &str) -> Grid {
-    use aoc_parse::{parser, prelude::*};
-    let point = parser!((x: usize) ',' * (y: usize) => (x, y));
-    let parser = parser!(lines(repeat_sep(point, " -> ")));
-    let data = parser.parse(input).unwrap();
-    let mut grid = vec![vec![false; GRID_SIZE]; GRID_SIZE];
-    for line in data {
-        for ((sx, sy), (ex, ey)) in line.windows(2).map(|w| (w[0], w[1])) {
-            assert!(
-                sx == ex || sy == ey,
-                "diagonal line: {:?} -> {:?}",
-                (sx, sy),
-                (ex, ey)
-            );
-            let (sx, ex) = if sx < ex { (sx, ex) } else { (ex, sx) };
-            let (sy, ey) = if sy < ey { (sy, ey) } else { (ey, sy) };
-            for x in sx..=ex {
-                for y in sy..=ey {
-                    grid[x][y] = true;
-                }
-            }
+impl Cell {
+    pub fn symbol(self) -> char {
+        match self {
+            Cell::Air => '.',
+            Cell::Rock => '#',
+            Cell::Sand => 'o',
+            Cell::Source => '+',
+            Cell::Water => '~',
+            Cell::Flowing => '|',
+        }
+    }
+
+    // water can sit on top of rock, sand and standing water, but not on water that's flowing
+    pub fn holds_water(self) -> bool {
+        matches!(self, Cell::Rock | Cell::Sand | Cell::Water)
+    }
+}
+
+// one bit per cell, each row packed into u64 words. Sand only cares whether a cell is solid,
+// and the three cells under a grain are at most two word reads away
+#[derive(Debug, Clone)]
+struct Bits {
+    // words per row
+    stride: usize,
+    words: Vec<u64>,
+}
+
+impl Bits {
+    fn new(width: usize, height: usize) -> Bits {
+        let stride = width.div_ceil(64);
+        // one spare word at the end, so `below` can always read two
+        Bits {
+            stride,
+            words: vec![0; stride * height + 1],
+        }
+    }
+
+    fn get(&self, x: usize, y: usize) -> bool {
+        self.words[y * self.stride + x / 64] >> (x % 64) & 1 == 1
+    }
+
+    fn set(&mut self, x: usize, y: usize) {
+        self.words[y * self.stride + x / 64] |= 1 << (x % 64);
+    }
+
+    fn row(&self, y: usize) -> &[u64] {
+        &self.words[y * self.stride..(y + 1) * self.stride]
+    }
+
+    fn row_mut(&mut self, y: usize) -> &mut [u64] {
+        &mut self.words[y * self.stride..(y + 1) * self.stride]
+    }
+
+    // the cells down-left, down and down-right of (x, y), as the bottom three bits
+    fn below(&self, x: usize, y: usize) -> u64 {
+        let start = x - 1;
+        let word = (y + 1) * self.stride + start / 64;
+        let pair = self.words[word] as u128 | (self.words[word + 1] as u128) << 64;
+        (pair >> (start % 64)) as u64 & 0b111
+    }
+
+    // where a grain at (x, y) moves to: down, down-left or down-right, if it can
+    fn step(&self, x: usize, y: usize) -> Option<usize> {
+        let below = self.below(x, y);
+        if below & 0b010 == 0 {
+            Some(x)
+        } else if below & 0b001 == 0 {
+            Some(x - 1)
+        } else if below & 0b100 == 0 {
+            Some(x + 1)
+        } else {
+            None
         }
     }
-    grid
 }
 
-fn print_grid(grid: &Grid) {
-    // find the min and max x and y
-    let mut min_x = GRID_SIZE;
-    let mut max_x = 0;
-    let mut min_y = GRID_SIZE;
-    let mut max_y = 0;
-    for x in 0..GRID_SIZE {
-        for y in 0..GRID_SIZE {
-            if grid[x][y] {
-                min_x = min_x.min(x);
-                max_x = max_x.max(x);
-                min_y = min_y.min(y);
-                max_y = max_y.max(y);
+// the part of the cave that sand can reach. Columns are indexed from `min_x`, so rock can be
+// anywhere (even at negative x), and the grid is only as big as it needs to be: wide enough
+// for the rock, and for the part 2 piles, which spread up to floor height (less the height of
+// the source) to each side of each source
+#[derive(Debug, Clone)]
+pub struct Cave {
+    // grid[x][y], with x offset by min_x
+    grid: Vec<Vec<Cell>>,
+    min_x: i64,
+    // the lowest rock. Below it is either the abyss (part 1) or the floor (part 2)
+    max_y: usize,
+    // in grid coordinates. A source inside rock is there, but blocked from the start
+    sources: Vec<(usize, usize)>,
+    // the rock and sand in `grid`. The sand solvers only ever copy and update this, and turn
+    // it back into cells (with `with_sand`) if someone wants to look at the result
+    solid: Bits,
+}
+
+impl Cave {
+    fn new(rocks: &[(i64, usize)], sources: &[(i64, usize)]) -> Cave {
+        let max_y = rocks.iter().map(|&(_, y)| y).max().unwrap_or(0);
+        let floor = max_y + 2;
+        for &source in sources {
+            assert!(source.1 < floor, "source below the floor: {:?}", source);
+        }
+        let spread = |&(_, y): &(i64, usize)| (floor - y) as i64;
+        // one extra column either side, so a grain can always look down-left and down-right
+        let min_x = rocks
+            .iter()
+            .map(|&(x, _)| x)
+            .chain(sources.iter().map(|source| source.0 - spread(source)))
+            .min()
+            .unwrap()
+            - 1;
+        let max_x = rocks
+            .iter()
+            .map(|&(x, _)| x)
+            .chain(sources.iter().map(|source| source.0 + spread(source)))
+            .max()
+            .unwrap()
+            + 1;
+        let (width, height) = ((max_x - min_x + 1) as usize, max_y + 3);
+        let mut grid = vec![vec![Cell::Air; height]; width];
+        let mut solid = Bits::new(width, height);
+        for &(x, y) in rocks {
+            grid[(x - min_x) as usize][y] = Cell::Rock;
+            solid.set((x - min_x) as usize, y);
+        }
+        let sources = sources
+            .iter()
+            .map(|&(x, y)| ((x - min_x) as usize, y))
+            .collect::<Vec<_>>();
+        for &(x, y) in &sources {
+            if grid[x][y] == Cell::Air {
+                grid[x][y] = Cell::Source;
             }
         }
+        Cave {
+            grid,
+            min_x,
+            max_y,
+            sources,
+            solid,
+        }
+    }
+
+    pub fn floor(&self) -> usize {
+        self.max_y + 2
+    }
+
+    pub fn width(&self) -> usize {
+        self.grid.len()
+    }
+
+    pub fn height(&self) -> usize {
+        self.floor() + 1
+    }
+
+    // (x, y) of the top-left cell
+    pub fn origin(&self) -> (i64, usize) {
+        (self.min_x, 0)
+    }
+
+    // anything outside the grid is air
+    pub fn get(&self, x: i64, y: usize) -> Cell {
+        if x < self.min_x {
+            return Cell::Air;
+        }
+        self.grid
+            .get((x - self.min_x) as usize)
+            .and_then(|col| col.get(y))
+            .copied()
+            .unwrap_or(Cell::Air)
+    }
+
+    pub fn is_rock(&self, x: i64, y: usize) -> bool {
+        self.get(x, y) == Cell::Rock
+    }
+
+    pub fn count(&self, cell: Cell) -> usize {
+        self.grid
+            .iter()
+            .map(|col| col.iter().filter(|&&c| c == cell).count())
+            .sum()
+    }
+
+    // this cave, with sand wherever `solid` has something this cave doesn't
+    fn with_sand(&self, solid: &Bits) -> Cave {
+        let mut cave = self.clone();
+        for (x, col) in cave.grid.iter_mut().enumerate() {
+            for (y, cell) in col.iter_mut().enumerate() {
+                if solid.get(x, y) && !self.solid.get(x, y) {
+                    *cell = Cell::Sand;
+                }
+            }
+        }
+        cave.solid = solid.clone();
+        cave
+    }
+
+    // in the order they take turns
+    pub fn sources(&self) -> Vec<(i64, usize)> {
+        self.sources
+            .iter()
+            .map(|&(x, y)| (x as i64 + self.min_x, y))
+            .collect()
     }
+}
+
+// every point on a horizontal, vertical or 45° line, including both ends
+fn segment((sx, sy): (i64, usize), (ex, ey): (i64, usize)) -> impl Iterator<Item = (i64, usize)> {
+    let (dx, dy) = (ex - sx, ey as i64 - sy as i64);
+    assert!(
+        dx == 0 || dy == 0 || dx.abs() == dy.abs(),
+        "line isn't straight or diagonal: {:?} -> {:?}",
+        (sx, sy),
+        (ex, ey)
+    );
+    let steps = dx.abs().max(dy.abs());
+    (0..=steps).map(move |i| (sx + i * dx.signum(), (sy as i64 + i * dy.signum()) as usize))
+}
+
+// every point inside the polygon with these corners (the edges get drawn separately): a ray cast right
+// from the point crosses an odd number of edges. Each edge includes its top end but not its
+// bottom, so a ray through a corner counts once (or twice, or not at all, at a peak)
+fn interior(corners: &[(i64, usize)]) -> Vec<(i64, usize)> {
+    let edges = corners
+        .iter()
+        .zip(corners.iter().cycle().skip(1))
+        .map(|(&(x1, y1), &(x2, y2))| ((x1, y1 as i64), (x2, y2 as i64)))
+        .collect::<Vec<_>>();
+    let (min_x, max_x) = corners
+        .iter()
+        .fold((i64::MAX, i64::MIN), |(lo, hi), &(x, _)| {
+            (lo.min(x), hi.max(x))
+        });
+    let (min_y, max_y) = corners
+        .iter()
+        .fold((usize::MAX, 0), |(lo, hi), &(_, y)| (lo.min(y), hi.max(y)));
+    let mut points = vec![];
     for y in min_y..=max_y {
+        let py = y as i64;
         for x in min_x..=max_x {
-            if grid[x][y] {
-                print!("#");
-            } else {
-                print!(".");
+            let crossings = edges
+                .iter()
+                .filter(|&&((x1, y1), (x2, y2))| {
+                    // the edge spans this row, and the point is to the left of it
+                    (y1 > py) != (y2 > py)
+                        && ((x - x1) * (y2 - y1) - (x2 - x1) * (py - y1)) * (y2 - y1).signum() < 0
+                })
+                .count();
+            if crossings % 2 == 1 {
+                points.push((x, y));
             }
         }
-        println!();
     }
+    points
 }
 
-pub fn part_1(input: &Grid) -> i64 {
-    let mut grid = input.clone();
-    let mut count = 0;
-    'outer: loop {
-        // print_grid(&grid);
-        // println!("Simulating sand ({})", count);
-        let mut x = 500;
-        let mut y = 0;
-        loop {
-            if y >= GRID_SIZE - 1 {
-                break 'outer;
+enum Line {
+    Path(Vec<(i64, usize)>),
+    Polygon(Vec<(i64, usize)>),
+    Source((i64, usize)),
+}
+
+// sources from the environment, like DAY14_SOURCES="500,0;520,0"
+fn env_sources() -> Vec<(i64, usize)> {
+    use aoc_parse::{parser, prelude::*};
+    let Ok(sources) = std::env::var("DAY14_SOURCES") else {
+        return vec![];
+    };
+    let parser = parser!(repeat_sep((x: i64) ',' (y: usize) => (x, y), ";"));
+    parser
+        .parse(sources.trim())
+        .unwrap_or_else(|err| panic!("bad DAY14_SOURCES: {}", err))
+}
+
+pub fn generator(input: &str) -> Cave {
+    generator_with_sources(input, &env_sources())
+}
+
+// input is a list of lines in the format x,y -> x,y [-> x,y]*, where each segment is either
+// horizontal, vertical or at 45°. A line starting with "fill " is a closed polygon instead,
+// and everything inside it is rock too. A line "source x,y" adds a source. `sources` (if there
+// are any) replace the ones in the input, and with neither, sand pours in from 500,0
+pub fn generator_with_sources(input: &str, sources: &[(i64, usize)]) -> Cave {
+    use aoc_parse::{parser, prelude::*};
+    let point = parser!((x: i64) ',' * (y: usize) => (x, y));
+    let path = parser!(repeat_sep(point, " -> "));
+    let line = parser!({
+        "source " (source: point) => Line::Source(source),
+        "fill " (corners: path) => Line::Polygon(corners),
+        (corners: path) => Line::Path(corners),
+    });
+    // aoc-main trims the input, so the last line might not end in a newline
+    let parser = parser!(repeat_sep(line, "\n"));
+    let data = parser.parse(input.trim_end()).unwrap();
+    let mut rocks = vec![];
+    let mut marked = vec![];
+    for line in data {
+        let line = match line {
+            Line::Path(line) => line,
+            Line::Polygon(mut line) => {
+                rocks.extend(interior(&line));
+                line.push(line[0]);
+                line
             }
-            // try to move down, down left, down right
-            if !grid[x][y + 1] {
-                y += 1;
-            } else if !grid[x - 1][y + 1] {
-                x -= 1;
-                y += 1;
-            } else if !grid[x + 1][y + 1] {
-                x += 1;
-                y += 1;
-            } else {
-                grid[x][y] = true;
-                count += 1;
-                break;
+            Line::Source(source) => {
+                marked.push(source);
+                continue;
             }
+        };
+        for w in line.windows(2) {
+            rocks.extend(segment(w[0], w[1]));
         }
     }
-    count
+    let sources = if !sources.is_empty() {
+        sources.to_vec()
+    } else if !marked.is_empty() {
+        marked
+    } else {
+        vec![SOURCE]
+    };
+    Cave::new(&rocks, &sources)
 }
 
-pub fn part_1_backtracking(input: &Grid) -> i64 {
-    let mut grid = input.clone();
-    let mut qq = VecDeque::new();
-    let mut count = 0;
-    qq.push_back((500, 0));
-    'outer: while let Some((x, y)) = qq.pop_back() {
-        if grid[x][y] {
-            continue;
+// the way the puzzle draws it: from the top of the cave down to the lowest thing in it, and
+// cropped to the columns with something in them
+impl fmt::Display for Cave {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let used = |col: &Vec<Cell>| col.iter().rposition(|&cell| cell != Cell::Air);
+        let columns = self
+            .grid
+            .iter()
+            .enumerate()
+            .filter_map(|(x, col)| used(col).map(|y| (x, y)))
+            .collect::<Vec<_>>();
+        let min_x = columns.first().map_or(0, |&(x, _)| x);
+        let max_x = columns.last().map_or(0, |&(x, _)| x);
+        let max_y = columns.iter().map(|&(_, y)| y).max().unwrap_or(0);
+        for y in 0..=max_y {
+            for col in &self.grid[min_x..=max_x] {
+                write!(f, "{}", col[y].symbol())?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+// what the solvers return: how many grains came to rest, and where. The cave with the sand
+// in it is only built if it's asked for
+#[derive(Debug, Clone)]
+pub struct Settled<'a> {
+    input: &'a Cave,
+    solid: Bits,
+    cave: OnceCell<Cave>,
+    count: i64,
+    per_source: Option<Vec<i64>>,
+}
+
+impl<'a> Settled<'a> {
+    fn new(input: &'a Cave, solid: Bits, count: i64, per_source: Option<Vec<i64>>) -> Self {
+        Settled {
+            input,
+            solid,
+            cave: OnceCell::new(),
+            count,
+            per_source,
+        }
+    }
+
+    pub fn count(&self) -> i64 {
+        self.count
+    }
+
+    // how many grains came from each source, in the order of Cave::sources. That depends on
+    // the order grains are dropped in, so solvers that don't drop grains can't say
+    pub fn per_source(&self) -> Option<&[i64]> {
+        self.per_source.as_deref()
+    }
+
+    pub fn cave(&self) -> &Cave {
+        self.cave.get_or_init(|| self.input.with_sand(&self.solid))
+    }
+}
+
+// the count, and with more than one source, how many came from each, like "93 (500,0: 50,
+// 497,1: 43)"
+impl fmt::Display for Settled<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.count)?;
+        match &self.per_source {
+            Some(per_source) if per_source.len() > 1 => {
+                let counts = self
+                    .input
+                    .sources()
+                    .iter()
+                    .zip(per_source)
+                    .map(|((x, y), count)| format!("{},{}: {}", x, y, count))
+                    .collect::<Vec<_>>();
+                write!(f, " ({})", counts.join(", "))
+            }
+            _ => Ok(()),
         }
-        let (mut x, mut y) = (x, y);
+    }
+}
+
+// what's under the lowest rock
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Bottom {
+    // sand falls forever (part 1)
+    Abyss,
+    // a floor two below the lowest rock (part 2)
+    Floor,
+}
+
+// a grain of sand that came to rest, which source (index into Cave::sources) it came from,
+// and (if the simulation is recording them) every cell it passed through on the way, from the
+// source to where it stopped
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Grain {
+    pub source: usize,
+    pub at: (i64, usize),
+    pub path: Vec<(i64, usize)>,
+}
+
+// pours sand one grain at a time, with the sources taking turns. It stops when a grain falls
+// into the abyss, or when every source is blocked
+#[derive(Debug, Clone)]
+pub struct SandSimulation<'a> {
+    cave: &'a Cave,
+    // the rock, and the sand so far
+    solid: Bits,
+    bottom: Bottom,
+    paths: bool,
+    next_source: usize,
+    done: bool,
+}
+
+impl<'a> SandSimulation<'a> {
+    pub fn new(cave: &'a Cave, bottom: Bottom) -> SandSimulation<'a> {
+        SandSimulation {
+            cave,
+            solid: cave.solid.clone(),
+            bottom,
+            paths: false,
+            next_source: 0,
+            done: false,
+        }
+    }
+
+    pub fn with_paths(mut self) -> SandSimulation<'a> {
+        self.paths = true;
+        self
+    }
+
+    // the cave with all the sand so far
+    pub fn cave(&self) -> Cave {
+        self.cave.with_sand(&self.solid)
+    }
+}
+
+impl Iterator for SandSimulation<'_> {
+    type Item = Grain;
+
+    fn next(&mut self) -> Option<Grain> {
+        if self.done {
+            return None;
+        }
+        let (min_x, max_y, sources) = (self.cave.min_x, self.cave.max_y, &self.cave.sources);
+        let solid = &mut self.solid;
+        // skip over any sources that are blocked
+        let open = (0..sources.len())
+            .map(|i| (self.next_source + i) % sources.len())
+            .find(|&i| !solid.get(sources[i].0, sources[i].1));
+        let Some(source) = open else {
+            self.done = true;
+            return None;
+        };
+        self.next_source = (source + 1) % sources.len();
+        let (mut x, mut y) = sources[source];
+        let mut path = vec![];
         loop {
-            if y >= GRID_SIZE - 1 {
-                break 'outer;
+            if self.paths {
+                path.push((x as i64 + min_x, y));
             }
-            qq.push_back((x, y));
-            // try to move down, down left, down right
-            if !grid[x][y + 1] {
-                y += 1;
-            } else if !grid[x - 1][y + 1] {
-                x -= 1;
-                y += 1;
-            } else if !grid[x + 1][y + 1] {
-                x += 1;
-                y += 1;
-            } else {
-                grid[x][y] = true;
-                count += 1;
-                break;
+            if y == max_y + 1 {
+                match self.bottom {
+                    Bottom::Abyss => {
+                        self.done = true;
+                        return None;
+                    }
+                    Bottom::Floor => break,
+                }
+            }
+            match solid.step(x, y) {
+                Some(next) => (x, y) = (next, y + 1),
+                None => break,
             }
         }
+        solid.set(x, y);
+        Some(Grain {
+            source,
+            at: (x as i64 + min_x, y),
+            path,
+        })
     }
-    count
 }
 
-// part 2: there's a floor at max_y + 2. Run the simulation until the start point (500, 0) is blocked
-pub fn part_2(input: &Grid) -> i64 {
-    let mut grid = input.clone();
-    let mut count = 0;
-    let floor = grid
+fn pour(input: &Cave, bottom: Bottom) -> Settled<'_> {
+    let mut sand = SandSimulation::new(input, bottom);
+    let mut per_source = vec![0; input.sources.len()];
+    for grain in sand.by_ref() {
+        per_source[grain.source] += 1;
+    }
+    let count = per_source.iter().sum();
+    Settled::new(input, sand.solid, count, Some(per_source))
+}
+
+// the same simulation, but each source remembers the path its last grain took. The next grain
+// from that source follows the same path up to the last cell that's still open (the cells
+// that made the earlier grain move the way it did are still solid), so it only needs to
+// simulate from there
+fn pour_backtracking(input: &Cave, bottom: Bottom) -> Settled<'_> {
+    let mut solid = input.solid.clone();
+    let mut paths = input
+        .sources
         .iter()
-        .map(|col| {
-            col.iter()
-                .enumerate()
-                .flat_map(|(idx, solid)| if *solid { Some(idx) } else { None })
-                .max()
-                .unwrap_or(0)
-        })
-        .max()
-        .unwrap()
-        + 2;
+        .map(|&source| VecDeque::from([source]))
+        .collect::<Vec<_>>();
+    let mut per_source = vec![0; paths.len()];
     'outer: loop {
-        // print_grid(&grid);
-        // println!("Simulating sand ({})", count);
-        let mut x = 500;
-        let mut y = 0;
-        loop {
-            if y == floor - 1 {
-                grid[x][y] = true;
-                count += 1;
-                break;
+        let mut dropped = false;
+        for (qq, count) in paths.iter_mut().zip(per_source.iter_mut()) {
+            while qq.back().is_some_and(|&(x, y)| solid.get(x, y)) {
+                qq.pop_back();
             }
-            // try to move down, down left, down right
-            if !grid[x][y + 1] {
-                y += 1;
-            } else if !grid[x - 1][y + 1] {
-                x -= 1;
-                y += 1;
-            } else if !grid[x + 1][y + 1] {
-                x += 1;
-                y += 1;
-            } else {
-                grid[x][y] = true;
-                count += 1;
-                if x == 500 && y == 0 {
-                    break 'outer;
+            // if the whole path is full, so is the source
+            let Some((mut x, mut y)) = qq.pop_back() else {
+                continue;
+            };
+            dropped = true;
+            loop {
+                if y == input.max_y + 1 {
+                    match bottom {
+                        Bottom::Abyss => break 'outer,
+                        Bottom::Floor => {
+                            solid.set(x, y);
+                            *count += 1;
+                            break;
+                        }
+                    }
+                }
+                qq.push_back((x, y));
+                match solid.step(x, y) {
+                    Some(next) => (x, y) = (next, y + 1),
+                    None => {
+                        solid.set(x, y);
+                        *count += 1;
+                        break;
+                    }
                 }
-                break;
             }
         }
+        if !dropped {
+            break;
+        }
     }
-    count
+    let count = per_source.iter().sum();
+    Settled::new(input, solid, count, Some(per_source))
+}
+
+pub fn part_1(input: &Cave) -> Settled<'_> {
+    pour(input, Bottom::Abyss)
+}
+
+pub fn part_1_backtracking(input: &Cave) -> Settled<'_> {
+    pour_backtracking(input, Bottom::Abyss)
+}
+
+// part 2: there's a floor at max_y + 2. Run the simulation until every source is blocked
+pub fn part_2(input: &Cave) -> Settled<'_> {
+    pour(input, Bottom::Floor)
 }
 
-pub fn part_2_backtracking(input: &Grid) -> i64 {
-    let mut grid = input.clone();
-    let mut qq = VecDeque::new();
+pub fn part_2_backtracking(input: &Cave) -> Settled<'_> {
+    pour_backtracking(input, Bottom::Floor)
+}
+
+// part 2 without simulating any grains: with a floor, every cell sand can get to ends up
+// full, and sand can get to a cell if it isn't rock, and it's a source or sand can get to one
+// of the three cells above it. So fill in the triangles under the sources a row at a time, a
+// word (64 cells) at a time
+pub fn part_2_rows(input: &Cave) -> Settled<'_> {
+    let mut solid = input.solid.clone();
+    let stride = input.solid.stride;
+    let top = input.sources.iter().map(|&(_, y)| y).min().unwrap();
     let mut count = 0;
-    let floor = grid
-        .iter()
-        .map(|col| {
-            col.iter()
-                .enumerate()
-                .flat_map(|(idx, solid)| if *solid { Some(idx) } else { None })
-                .max()
-                .unwrap_or(0)
-        })
-        .max()
-        .unwrap()
-        + 2;
-    qq.push_back((500, 0));
-    'outer: while let Some((x, y)) = qq.pop_back() {
-        if grid[x][y] {
-            continue;
+    let mut prev = vec![0; stride];
+    for y in top..input.floor() {
+        let mut row = (0..stride)
+            .map(|i| {
+                // the cells above-right and above-left, carrying bits across words
+                let from_left = prev[i] << 1 | if i > 0 { prev[i - 1] >> 63 } else { 0 };
+                let from_right = prev[i] >> 1 | if i + 1 < stride { prev[i + 1] << 63 } else { 0 };
+                prev[i] | from_left | from_right
+            })
+            .collect::<Vec<_>>();
+        for &(x, _) in input.sources.iter().filter(|&&(_, sy)| sy == y) {
+            row[x / 64] |= 1 << (x % 64);
         }
-        let (mut x, mut y) = (x, y);
-        loop {
-            if y == floor - 1 {
-                grid[x][y] = true;
-                count += 1;
-                break;
+        for (word, (sand, &rock)) in solid
+            .row_mut(y)
+            .iter_mut()
+            .zip(row.iter_mut().zip(input.solid.row(y)))
+        {
+            *sand &= !rock;
+            count += sand.count_ones() as i64;
+            *word |= *sand;
+        }
+        prev = row;
+    }
+    let per_source = (input.sources.len() == 1).then(|| vec![count]);
+    Settled::new(input, solid, count, per_source)
+}
+
+// pouring water into the cave instead of sand (only into the abyss: with a floor it would
+// spread forever). Water falls until it lands on something, then spreads both ways. If it
+// hits a wall on both sides, it fills that row of the basin and rises a row; otherwise it
+// flows over whichever edges it reaches
+#[derive(Debug, Clone)]
+pub struct Flooded {
+    cave: Cave,
+    wet: usize,
+    standing: usize,
+}
+
+impl Flooded {
+    // every cell water got to
+    pub fn wet(&self) -> usize {
+        self.wet
+    }
+
+    pub fn standing(&self) -> usize {
+        self.standing
+    }
+
+    pub fn cave(&self) -> &Cave {
+        &self.cave
+    }
+}
+
+impl fmt::Display for Flooded {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} wet, {} standing", self.wet, self.standing)
+    }
+}
+
+// how far water on row y spreads from x in direction dx: either to the last cell before a wall
+// (false), or to the first cell with nothing under it to hold the water up (true)
+fn spread(grid: &[Vec<Cell>], x: usize, y: usize, dx: isize) -> (usize, bool) {
+    let mut x = x;
+    loop {
+        if !grid[x][y + 1].holds_water() {
+            return (x, true);
+        }
+        let next = x.wrapping_add_signed(dx);
+        if grid[next][y].holds_water() {
+            return (x, false);
+        }
+        x = next;
+    }
+}
+
+// water falling from `start`. Whatever it fills never rises above `start`: the caller deals
+// with that row
+fn flow(grid: &mut [Vec<Cell>], (x, start): (usize, usize), max_y: usize) {
+    let mut y = start;
+    loop {
+        if grid[x][y] == Cell::Flowing {
+            // already been here
+            return;
+        }
+        grid[x][y] = Cell::Flowing;
+        if y == max_y {
+            // nothing below but the abyss
+            return;
+        }
+        if grid[x][y + 1].holds_water() {
+            break;
+        }
+        y += 1;
+    }
+    loop {
+        let (left, left_open) = spread(grid, x, y, -1);
+        let (right, right_open) = spread(grid, x, y, 1);
+        if !left_open && !right_open {
+            for col in &mut grid[left..=right] {
+                col[y] = Cell::Water;
             }
-            qq.push_back((x, y));
-            // try to move down, down left, down right
-            if !grid[x][y + 1] {
-                y += 1;
-            } else if !grid[x - 1][y + 1] {
-                x -= 1;
-                y += 1;
-            } else if !grid[x + 1][y + 1] {
-                x += 1;
-                y += 1;
-            } else {
-                grid[x][y] = true;
-                count += 1;
-                if x == 500 && y == 0 {
-                    break 'outer;
-                }
-                break;
+            if y == start {
+                return;
             }
+            y -= 1;
+            continue;
+        }
+        for col in &mut grid[left..=right] {
+            col[y] = Cell::Flowing;
+        }
+        let mut filled = false;
+        for (edge, open) in [(left, left_open), (right, right_open)] {
+            if open {
+                flow(grid, (edge, y + 1), max_y);
+                // the water over the edge might have filled a basin up to this row, in which
+                // case this row has to spread again
+                filled |= grid[edge][y + 1].holds_water();
+            }
+        }
+        if !filled {
+            return;
+        }
+    }
+}
+
+pub fn water(input: &Cave) -> Flooded {
+    let mut cave = input.clone();
+    for &(x, y) in &input.sources {
+        if y < input.max_y && !cave.grid[x][y + 1].holds_water() {
+            flow(&mut cave.grid, (x, y + 1), input.max_y);
+        }
+    }
+    let standing = cave.count(Cell::Water);
+    Flooded {
+        wet: standing + cave.count(Cell::Flowing),
+        standing,
+        cave,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE_INPUT: &str = "498,4 -> 498,6 -> 496,6
+503,4 -> 502,4 -> 502,9 -> 494,9
+";
+
+    // the sources from the input (or 500,0), whatever DAY14_SOURCES is set to
+    fn generator(input: &str) -> Cave {
+        generator_with_sources(input, &[])
+    }
+
+    #[test]
+    fn test_generator() {
+        let cave = generator(SAMPLE_INPUT);
+        assert_eq!(cave.floor(), 11);
+        assert_eq!(cave.height(), 12);
+        // wide enough for the part 2 pile, plus a column either side
+        assert_eq!(cave.origin(), (500 - 11 - 1, 0));
+        assert_eq!(cave.width(), 2 * 11 + 3);
+        assert!(cave.is_rock(498, 4));
+        assert!(cave.is_rock(494, 9));
+        assert!(!cave.is_rock(500, 0));
+        assert!(!cave.is_rock(-1000, 9));
+        assert!(!cave.is_rock(500, 1000));
+        // without the trailing newline, the way aoc-main passes it in
+        let trimmed = generator(SAMPLE_INPUT.trim_end());
+        assert_eq!(trimmed.to_string(), cave.to_string());
+    }
+
+    #[test]
+    fn test_sample() {
+        let cave = generator(SAMPLE_INPUT);
+        assert_eq!(part_1(&cave).count(), 24);
+        assert_eq!(part_1_backtracking(&cave).count(), 24);
+        assert_eq!(part_2(&cave).count(), 93);
+        assert_eq!(part_2_backtracking(&cave).count(), 93);
+        assert_eq!(part_2_rows(&cave).count(), 93);
+    }
+
+    #[test]
+    fn test_far_away_rock() {
+        // rock way off to either side (even at negative x) just makes the cave wider
+        let cave = generator(&format!(
+            "{}-3000,2 -> -2990,2\n9000,5 -> 9000,7\n",
+            SAMPLE_INPUT
+        ));
+        assert_eq!(cave.origin(), (-3001, 0));
+        assert_eq!(cave.width(), 9000 + 3001 + 2);
+        assert!(cave.is_rock(-2995, 2));
+        assert_eq!(part_1(&cave).count(), 24);
+        assert_eq!(part_1_backtracking(&cave).count(), 24);
+        assert_eq!(part_2(&cave).count(), 93);
+        assert_eq!(part_2_backtracking(&cave).count(), 93);
+        assert_eq!(part_2_rows(&cave).count(), 93);
+    }
+
+    #[test]
+    fn test_render() {
+        let cave = generator(SAMPLE_INPUT);
+        assert_eq!(cave.get(500, 0), Cell::Source);
+        assert_eq!(
+            cave.to_string(),
+            "......+...
+..........
+..........
+..........
+....#...##
+....#...#.
+..###...#.
+........#.
+........#.
+#########.
+"
+        );
+        for settled in [part_1(&cave), part_1_backtracking(&cave)] {
+            assert_eq!(
+                settled.cave().to_string(),
+                "......+...
+..........
+......o...
+.....ooo..
+....#ooo##
+...o#ooo#.
+..###ooo#.
+....oooo#.
+.o.ooooo#.
+#########.
+"
+            );
+            assert_eq!(settled.cave().count(Cell::Sand), 24);
+            assert_eq!(settled.cave().count(Cell::Rock), cave.count(Cell::Rock));
+        }
+        for settled in [
+            part_2(&cave),
+            part_2_backtracking(&cave),
+            part_2_rows(&cave),
+        ] {
+            // the last grain covers the source
+            assert_eq!(settled.cave().get(500, 0), Cell::Sand);
+            assert_eq!(settled.cave().count(Cell::Source), 0);
+            assert_eq!(settled.cave().count(Cell::Sand), 93);
+        }
+    }
+
+    #[test]
+    fn test_sand_simulation() {
+        let cave = generator(SAMPLE_INPUT);
+        let mut sand = SandSimulation::new(&cave, Bottom::Abyss).with_paths();
+        // the first grain falls straight down onto the bottom rock
+        let first = sand.next().unwrap();
+        assert_eq!(first.at, (500, 8));
+        assert_eq!(first.path, (0..=8).map(|y| (500, y)).collect::<Vec<_>>());
+        let second = sand.next().unwrap();
+        assert_eq!(second.at, (499, 8));
+        assert_eq!(second.path.last(), Some(&(499, 8)));
+        assert_eq!(second.path.len(), 9);
+        assert_eq!(sand.cave().count(Cell::Sand), 2);
+        // the last grain lands at the far left, and the next one falls into the abyss
+        let rest = sand.by_ref().collect::<Vec<_>>();
+        assert_eq!(rest.len(), 22);
+        assert_eq!(rest.last().unwrap().at, (495, 8));
+        assert_eq!(sand.next(), None);
+
+        // with a floor, sand only stops once the source is covered
+        let sand = SandSimulation::new(&cave, Bottom::Floor);
+        let grains = sand.collect::<Vec<_>>();
+        assert_eq!(grains.len(), 93);
+        assert_eq!(grains.last().unwrap().at, (500, 0));
+        assert!(grains.iter().all(|grain| grain.path.is_empty()));
+    }
+
+    #[test]
+    fn test_part_2_rows() {
+        // rock that shadows the cells under it, a ledge the pile spills over, and a cup that
+        // sand can't get into from above
+        let input = format!(
+            "{}490,3 -> 492,3\n505,2 -> 505,5 -> 509,5 -> 509,2\n",
+            SAMPLE_INPUT
+        );
+        let cave = generator(&input);
+        let expected = part_2(&cave);
+        for settled in [part_2_backtracking(&cave), part_2_rows(&cave)] {
+            assert_eq!(settled.count(), expected.count());
+            assert_eq!(settled.cave().to_string(), expected.cave().to_string());
+        }
+    }
+
+    #[test]
+    fn test_diagonals() {
+        let cave = generator("497,3 -> 500,6 -> 503,3\n");
+        assert_eq!(
+            cave.to_string(),
+            "...+...
+.......
+.......
+#.....#
+.#...#.
+..#.#..
+...#...
+"
+        );
+        // the sand rules are the same, so a grain can slip diagonally between two rocks that
+        // only touch at the corners: the V doesn't hold anything
+        let mut sand = SandSimulation::new(&cave, Bottom::Abyss);
+        assert_eq!(sand.next(), None);
+        assert_eq!(part_1_backtracking(&cave).count(), 0);
+        let mut sand = SandSimulation::new(&cave, Bottom::Floor).with_paths();
+        let first = sand.next().unwrap();
+        assert!(first.path.contains(&(500, 5)));
+        assert!(first.path.contains(&(499, 6)));
+        let expected = part_2(&cave);
+        for settled in [part_2_backtracking(&cave), part_2_rows(&cave)] {
+            assert_eq!(settled.count(), expected.count());
+            assert_eq!(settled.cave().to_string(), expected.cave().to_string());
+        }
+    }
+
+    #[test]
+    fn test_polygons() {
+        // a diamond and a triangle. Closing the outline is implicit
+        let cave =
+            generator("fill 500,3 -> 503,6 -> 500,9 -> 497,6\nfill 490,12 -> 496,12 -> 493,15\n");
+        assert_eq!(
+            cave.to_string(),
+            "..........+...
+..............
+..............
+..........#...
+.........###..
+........#####.
+.......#######
+........#####.
+.........###..
+..........#...
+..............
+..............
+#######.......
+.#####........
+..###.........
+...#..........
+"
+        );
+        let part_1s = [part_1(&cave), part_1_backtracking(&cave)];
+        assert_eq!(part_1s[0].count(), part_1s[1].count());
+        assert_eq!(part_1s[0].cave().to_string(), part_1s[1].cave().to_string());
+        let expected = part_2(&cave);
+        for settled in [part_2_backtracking(&cave), part_2_rows(&cave)] {
+            assert_eq!(settled.count(), expected.count());
+            assert_eq!(settled.cave().to_string(), expected.cave().to_string());
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "isn't straight or diagonal")]
+    fn test_other_angles() {
+        generator("498,4 -> 500,5\n");
+    }
+
+    #[test]
+    fn test_sources() {
+        let cave = generator(SAMPLE_INPUT);
+        assert_eq!(cave.sources(), vec![(500, 0)]);
+        assert_eq!(part_2(&cave).per_source(), Some(&[93][..]));
+        assert_eq!(part_2_rows(&cave).per_source(), Some(&[93][..]));
+
+        // marked in the input, or passed in (which wins)
+        let input = format!("{}source 496,0\nsource 503,2\n", SAMPLE_INPUT);
+        let cave = generator_with_sources(&input, &[]);
+        assert_eq!(cave.sources(), vec![(496, 0), (503, 2)]);
+        assert_eq!(cave.get(503, 2), Cell::Source);
+        let cave = generator_with_sources(&input, &[(500, 0), (497, 1)]);
+        assert_eq!(cave.sources(), vec![(500, 0), (497, 1)]);
+
+        // the sources take turns
+        let grains = SandSimulation::new(&cave, Bottom::Floor).collect::<Vec<_>>();
+        assert_eq!(grains[0].source, 0);
+        assert_eq!(grains[1].source, 1);
+        assert_eq!(grains[2].source, 0);
+        let part_1s = [part_1(&cave), part_1_backtracking(&cave)];
+        assert_eq!(part_1s[0].per_source(), part_1s[1].per_source());
+        assert_eq!(part_1s[0].cave().to_string(), part_1s[1].cave().to_string());
+        let expected = part_2(&cave);
+        assert_eq!(expected.count(), grains.len() as i64);
+        let per_source = expected.per_source().unwrap();
+        assert_eq!(per_source.iter().sum::<i64>(), expected.count());
+        assert!(per_source.iter().all(|&count| count > 0));
+        for settled in [part_2_backtracking(&cave), part_2_rows(&cave)] {
+            assert_eq!(settled.count(), expected.count());
+            assert_eq!(settled.cave().to_string(), expected.cave().to_string());
+        }
+        assert_eq!(part_2_backtracking(&cave).per_source(), Some(per_source));
+        // which source each grain came from depends on the order they fell in
+        assert_eq!(part_2_rows(&cave).per_source(), None);
+    }
+
+    #[test]
+    fn test_blocked_source() {
+        // a source in rock never drops anything; the others carry on without it
+        let cave = generator_with_sources(SAMPLE_INPUT, &[(498, 5), (500, 0)]);
+        assert_eq!(cave.get(498, 5), Cell::Rock);
+        assert_eq!(part_2(&cave).per_source(), Some(&[0, 93][..]));
+        assert_eq!(part_2_backtracking(&cave).per_source(), Some(&[0, 93][..]));
+        assert_eq!(part_2(&cave).to_string(), "93 (498,5: 0, 500,0: 93)");
+        assert_eq!(part_2_rows(&cave).to_string(), "93");
+        assert_eq!(part_2_rows(&cave).count(), 93);
+        let cave = generator_with_sources(SAMPLE_INPUT, &[(498, 5)]);
+        assert_eq!(part_1(&cave).count(), 0);
+        assert_eq!(part_2_backtracking(&cave).count(), 0);
+        assert_eq!(part_2_rows(&cave).count(), 0);
+    }
+
+    #[test]
+    fn test_water() {
+        let cave = generator(
+            "495,2 -> 495,7 -> 501,7 -> 501,3
+498,2 -> 498,4
+506,1 -> 506,2
+498,10 -> 498,13 -> 504,13 -> 504,10
+",
+        );
+        let flooded = water(&cave);
+        assert_eq!(flooded.wet(), 57);
+        assert_eq!(flooded.standing(), 29);
+        assert_eq!(flooded.to_string(), "57 wet, 29 standing");
+        assert_eq!(
+            flooded.cave().to_string(),
+            ".....+......
+.....|.....#
+#..#||||...#
+#..#~~#|....
+#..#~~#|....
+#~~~~~#|....
+#~~~~~#|....
+#######|....
+.......|....
+..|||||||||.
+..|#~~~~~#|.
+..|#~~~~~#|.
+..|#~~~~~#|.
+..|#######|.
+"
+        );
+    }
+
+    #[test]
+    fn test_water_overflow() {
+        // a basin inside a basin, fed from two sources: the inner one fills, then the outer
+        // one, then it spills over the lower (right) wall
+        let input = "source 500,0
+source 503,0
+494,2 -> 494,8 -> 508,8 -> 508,4
+499,5 -> 499,7
+502,5 -> 502,7
+";
+        let flooded = water(&generator(input));
+        let cave = flooded.cave();
+        assert_eq!(cave.get(500, 6), Cell::Water);
+        assert_eq!(cave.get(495, 4), Cell::Water);
+        assert_eq!(cave.get(495, 3), Cell::Flowing);
+        assert_eq!(cave.get(509, 8), Cell::Flowing);
+        // four rows deep, less the inner walls
+        assert_eq!(flooded.standing(), 13 * 4 - 2 * 3);
+        // the sand simulation doesn't know about water
+        assert_eq!(cave.count(Cell::Sand), 0);
+    }
+
+    #[test]
+    fn test_bits() {
+        let mut bits = Bits::new(200, 3);
+        assert_eq!(bits.stride, 4);
+        for x in [0, 62, 63, 64, 127, 128, 199] {
+            bits.set(x, 1);
         }
+        assert!(bits.get(63, 1) && bits.get(64, 1) && !bits.get(65, 1) && !bits.get(63, 0));
+        assert_eq!(bits.below(1, 0), 0b001);
+        // across word boundaries
+        assert_eq!(bits.below(63, 0), 0b111);
+        assert_eq!(bits.below(64, 0), 0b011);
+        assert_eq!(bits.below(65, 0), 0b001);
+        assert_eq!(bits.below(127, 0), 0b110);
+        assert_eq!(bits.below(128, 0), 0b011);
+        assert_eq!(bits.step(63, 0), None);
+        assert_eq!(bits.step(65, 0), Some(65));
+        assert_eq!(bits.step(128, 0), Some(129));
+        assert_eq!(bits.step(126, 0), Some(126));
+        assert_eq!(bits.row(1), &[1 | 3 << 62, 1 | 1 << 63, 1, 1 << 7][..]);
     }
-    count
 }
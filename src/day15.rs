@@ -1,100 +1,377 @@
+use crate::intervals::IntervalSet;
 use std::ops::RangeInclusive;
 
 pub type Point = (i64, i64);
 // position of the sensor, position of the closest beacon
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Sensor(Point, Point);
 
+// the row part 1 asks about, and the search area for part 2. The example in the puzzle uses
+// different ones, so they can be overridden with DAY15_ROW and DAY15_BOUND
+const ROW: i64 = 2_000_000;
+const BOUND: i64 = 4_000_000;
+
+// a number from the environment, if it's set
+fn env_param(name: &str) -> Option<i64> {
+    let value = std::env::var(name).ok()?;
+    Some(
+        value
+            .trim()
+            .parse()
+            .unwrap_or_else(|_| panic!("{} should be a number, not {:?}", name, value)),
+    )
+}
+
+impl Sensor {
+    pub fn position(&self) -> Point {
+        self.0
+    }
+
+    pub fn beacon(&self) -> Point {
+        self.1
+    }
+}
+
 // "Sensor at x=2, y=18: closest beacon is at x=-2, y=15"
 pub fn generator(input: &str) -> Vec<Sensor> {
     use aoc_parse::{parser, prelude::*};
-    let parser = parser!(lines("Sensor at x=" (x: i64) ", y=" (y: i64) ": closest beacon is at x=" (x2: i64) ", y=" (y2: i64) => Sensor((x, y), (x2, y2))));
-    parser.parse(input).unwrap()
+    let sensor = parser!("Sensor at x=" (x: i64) ", y=" (y: i64) ": closest beacon is at x=" (x2: i64) ", y=" (y2: i64) => Sensor((x, y), (x2, y2)));
+    // aoc-main trims the input, so the last line might not end in a newline
+    let parser = parser!(repeat_sep(sensor, "\n"));
+    parser.parse(input.trim_end()).unwrap()
 }
 
 // nb: manhattan distance
 struct Circle(Point, i64); // position of the beacon, area (nb: manhattan distance)
 
-// return the range of x values that are in the circle
+impl Circle {
+    fn contains(&self, (x, y): Point) -> bool {
+        let Circle((cx, cy), radius) = self;
+        (x - cx).abs() + (y - cy).abs() <= *radius
+    }
+}
+
+fn circles(input: &[Sensor]) -> Vec<Circle> {
+    input
+        .iter()
+        .map(|Sensor(pos, closest)| {
+            let radius = (pos.0 - closest.0).abs() + (pos.1 - closest.1).abs();
+            Circle(*pos, radius)
+        })
+        .collect()
+}
+
+// return the range of x values that are in the circle. It's empty (start > end) if row y
+// doesn't reach the circle
 fn range_in(y: i64, circle: &Circle) -> RangeInclusive<i64> {
     let Circle((cx, cy), radius) = circle;
     let ydiff = (y - cy).abs();
-    // ydiff + x = cx - radius
-    let x1 = cx - radius - ydiff;
-    let x2 = cx + radius - ydiff;
+    // |x - cx| + ydiff <= radius
+    let x1 = cx - (radius - ydiff);
+    let x2 = cx + (radius - ydiff);
     x1..=x2
 }
 
-fn overlapping_intervals(intervals: &[RangeInclusive<i64>]) -> Vec<RangeInclusive<i64>> {
-    let mut intervals = intervals.to_vec();
-    intervals.sort_by_key(|r| *r.start());
-    // visualize them as lines
-    let minx = intervals.iter().map(|r| *r.start()).min().unwrap();
-    for it in intervals.iter() {
-        let start = it.start() - minx;
-        let end = it.end() - minx;
-        println!(
-            "{} {}",
-            ".".repeat(start as usize),
-            "#".repeat((end - start) as usize)
-        );
+// the sensors, with how far each one can see, for asking questions about particular points
+pub struct SensorField {
+    sensors: Vec<Sensor>,
+    circles: Vec<Circle>,
+}
+
+impl SensorField {
+    pub fn new(input: &[Sensor]) -> SensorField {
+        SensorField {
+            sensors: input.to_vec(),
+            circles: circles(input),
+        }
+    }
+
+    pub fn sensors(&self) -> &[Sensor] {
+        &self.sensors
+    }
+
+    // the distance from sensor i to its beacon: it covers everything that close or closer
+    pub fn radius(&self, i: usize) -> i64 {
+        self.circles[i].1
+    }
+
+    pub fn is_covered(&self, p: Point) -> bool {
+        self.circles.iter().any(|c| c.contains(p))
+    }
+
+    // indices of the sensors that cover p
+    pub fn covering(&self, p: Point) -> Vec<usize> {
+        (0..self.circles.len())
+            .filter(|&i| self.circles[i].contains(p))
+            .collect()
+    }
+
+    // the closest sensor to p, and how far away it is. If there's a tie, the first one
+    pub fn nearest(&self, (x, y): Point) -> Option<(usize, i64)> {
+        self.circles
+            .iter()
+            .map(|Circle((cx, cy), _)| (x - cx).abs() + (y - cy).abs())
+            .enumerate()
+            .min_by_key(|&(i, distance)| (distance, i))
+    }
+
+    // every point in the rectangle that no sensor covers, a row at a time from the top left
+    pub fn uncovered_in(
+        &self,
+        xs: RangeInclusive<i64>,
+        ys: RangeInclusive<i64>,
+    ) -> impl Iterator<Item = Point> + '_ {
+        ys.flat_map(move |y| {
+            let covered = self
+                .circles
+                .iter()
+                .map(|c| range_in(y, c))
+                .collect::<IntervalSet>();
+            let gaps = covered.gaps_in(xs.clone());
+            gaps.iter().flatten().map(|x| (x, y)).collect::<Vec<_>>()
+        })
+    }
+}
+
+pub fn part_1(input: &[Sensor]) -> i64 {
+    no_beacons_in_row(input, env_param("DAY15_ROW").unwrap_or(ROW))
+}
+
+// how many positions in row y can't have a beacon in them
+pub fn no_beacons_in_row(input: &[Sensor], y: i64) -> i64 {
+    let mut covered = circles(input)
+        .iter()
+        .map(|c| range_in(y, c))
+        .collect::<IntervalSet>();
+    // except where there already is a beacon. Sensors can share a beacon, but taking it out of
+    // the set twice is harmless
+    for Sensor(_, beacon) in input.iter().filter(|Sensor(_, beacon)| beacon.1 == y) {
+        covered.remove_point(beacon.0);
+    }
+    covered.len() as i64
+}
+
+// the distress beacon, somewhere in 0..=bound in both directions. If there's only one point
+// the sensors don't cover, its neighbours are all covered, so it's just outside at least one
+// sensor's diamond: on one of the diagonal lines one step out from its edges. With only one
+// such point, it's usually where two of those lines cross, or where one meets the edge of the
+// search area, so those points get checked first. But it can also sit in a gap between two
+// parallel lines, with nothing crossing it, so if none of them are free, fall back to scanning
+// the whole area a row at a time
+pub fn distress_beacon(input: &[Sensor], bound: i64) -> Option<Point> {
+    let field = SensorField::new(input);
+    let circles = &field.circles;
+    // the lines are x + y = a and x - y = b
+    let mut ups = vec![0, 2 * bound];
+    let mut downs = vec![-bound, bound];
+    for Circle((cx, cy), radius) in circles.iter() {
+        let d = radius + 1;
+        ups.extend([cx + cy - d, cx + cy + d]);
+        downs.extend([cx - cy - d, cx - cy + d]);
     }
-    let mut result = vec![];
-    let mut current = intervals[0].clone();
-    for interval in intervals[1..].iter() {
-        if current.start() > current.end() {
-            panic!("current interval is empty: {:?}", current)
+    let mut candidates = vec![];
+    for &a in ups.iter() {
+        for &b in downs.iter() {
+            if (a + b) % 2 == 0 {
+                candidates.push(((a + b) / 2, (a - b) / 2));
+            }
         }
-        if interval.start() <= current.end() {
-            let start = current.start();
-            let end = std::cmp::max(current.end(), interval.end());
-            current = *start..=*end;
-        } else {
-            result.push(current);
-            current = interval.clone();
+        candidates.extend([(0, a), (bound, a - bound), (a, 0), (a - bound, bound)]);
+    }
+    for &b in downs.iter() {
+        candidates.extend([(0, -b), (bound, bound - b), (b, 0), (b + bound, bound)]);
+    }
+    candidates
+        .into_iter()
+        .find(|&(x, y)| {
+            (0..=bound).contains(&x) && (0..=bound).contains(&y) && !field.is_covered((x, y))
+        })
+        .or_else(|| field.uncovered_in(0..=bound, 0..=bound).next())
+}
+
+pub fn tuning_frequency((x, y): Point) -> i64 {
+    x * 4_000_000 + y
+}
+
+pub fn part_2(input: &[Sensor]) -> i64 {
+    let beacon = distress_beacon(input, env_param("DAY15_BOUND").unwrap_or(BOUND))
+        .expect("every point in the search area is covered");
+    tuning_frequency(beacon)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // the example from the puzzle, which asks about row 10, and a search area of 0..=20
+    const SAMPLE: &str = "Sensor at x=2, y=18: closest beacon is at x=-2, y=15
+Sensor at x=9, y=16: closest beacon is at x=10, y=16
+Sensor at x=13, y=2: closest beacon is at x=15, y=3
+Sensor at x=12, y=14: closest beacon is at x=10, y=16
+Sensor at x=10, y=20: closest beacon is at x=10, y=16
+Sensor at x=14, y=17: closest beacon is at x=10, y=16
+Sensor at x=8, y=7: closest beacon is at x=2, y=10
+Sensor at x=2, y=0: closest beacon is at x=2, y=10
+Sensor at x=0, y=11: closest beacon is at x=2, y=10
+Sensor at x=20, y=14: closest beacon is at x=25, y=17
+Sensor at x=17, y=20: closest beacon is at x=21, y=22
+Sensor at x=16, y=7: closest beacon is at x=15, y=3
+Sensor at x=14, y=3: closest beacon is at x=15, y=3
+Sensor at x=20, y=1: closest beacon is at x=15, y=3
+";
+
+    // every uncovered point in 0..=bound, the slow way
+    fn uncovered(input: &[Sensor], bound: i64) -> Vec<Point> {
+        let circles = circles(input);
+        (0..=bound)
+            .flat_map(|y| (0..=bound).map(move |x| (x, y)))
+            .filter(|&p| !circles.iter().any(|c| c.contains(p)))
+            .collect()
+    }
+
+    #[test]
+    fn test_part_2() {
+        let input = generator(SAMPLE);
+        assert_eq!(uncovered(&input, 20), vec![(14, 11)]);
+        assert_eq!(distress_beacon(&input, 20), Some((14, 11)));
+        assert_eq!(tuning_frequency((14, 11)), 56000011);
+    }
+
+    #[test]
+    fn test_distress_beacon_on_the_edge() {
+        // one sensor covering everything in 0..=4 but the corners, and three small ones
+        // covering all the corners but one. That one's only next to one diagonal line
+        let input = generator(
+            "Sensor at x=2, y=2: closest beacon is at x=2, y=5
+Sensor at x=-1, y=0: closest beacon is at x=-2, y=0
+Sensor at x=-1, y=4: closest beacon is at x=-2, y=4
+Sensor at x=4, y=-1: closest beacon is at x=4, y=-2
+",
+        );
+        assert_eq!(uncovered(&input, 4), vec![(4, 4)]);
+        assert_eq!(distress_beacon(&input, 4), Some((4, 4)));
+        // and a bigger area, where there's more than one
+        assert_eq!(
+            distress_beacon(&input, 10).map(|p| uncovered(&input, 10).contains(&p)),
+            Some(true)
+        );
+    }
+
+    #[test]
+    fn test_distress_beacon_between_lines() {
+        // the only gap is between two parallel x - y = 1 lines, and no line crosses it
+        let input = generator(
+            "Sensor at x=2, y=1: closest beacon is at x=6, y=3
+Sensor at x=4, y=0: closest beacon is at x=8, y=1
+Sensor at x=3, y=9: closest beacon is at x=6, y=12
+Sensor at x=3, y=8: closest beacon is at x=4, y=12
+Sensor at x=8, y=8: closest beacon is at x=8, y=11
+Sensor at x=8, y=3: closest beacon is at x=8, y=6
+",
+        );
+        assert_eq!(uncovered(&input, 9), vec![(6, 5)]);
+        assert_eq!(distress_beacon(&input, 9), Some((6, 5)));
+    }
+
+    #[test]
+    fn test_generator() {
+        let input = generator(SAMPLE);
+        assert_eq!(input.len(), 14);
+        assert_eq!(input[0], Sensor((2, 18), (-2, 15)));
+        // without the trailing newline, the way aoc-main passes it in
+        assert_eq!(generator(SAMPLE.trim_end()), input);
+    }
+
+    // every position in row y that a sensor covers, less the beacons, one at a time
+    fn no_beacons_slow(input: &[Sensor], y: i64) -> i64 {
+        let circles = circles(input);
+        (-100..=100)
+            .filter(|&x| circles.iter().any(|c| c.contains((x, y))))
+            .filter(|&x| !input.iter().any(|Sensor(_, beacon)| *beacon == (x, y)))
+            .count() as i64
+    }
+
+    #[test]
+    fn test_part_1() {
+        let input = generator(SAMPLE);
+        assert_eq!(no_beacons_in_row(&input, 10), 26);
+        for y in -5..=25 {
+            assert_eq!(
+                no_beacons_in_row(&input, y),
+                no_beacons_slow(&input, y),
+                "{}",
+                y
+            );
         }
+        // past the bottom of every sensor's range
+        assert_eq!(no_beacons_in_row(&input, 100), 0);
+    }
+
+    #[test]
+    fn test_range_in() {
+        let circle = Circle((8, 7), 9);
+        assert_eq!(range_in(7, &circle), -1..=17);
+        assert_eq!(range_in(10, &circle), 2..=14);
+        assert_eq!(range_in(16, &circle), 8..=8);
+        assert!(range_in(17, &circle).is_empty());
+        assert!(range_in(-3, &circle).is_empty());
     }
-    result.push(current);
-    for it in result.iter() {
-        let start = it.start() - minx;
-        let end = it.end() - minx;
-        println!(
-            "{} {}",
-            ".".repeat(start as usize),
-            "X".repeat((end - start) as usize)
+
+    #[test]
+    fn test_shared_beacon() {
+        // two sensors with the same beacon (on the row): it's only taken off once
+        let input = generator(
+            "Sensor at x=0, y=0: closest beacon is at x=2, y=2
+Sensor at x=4, y=0: closest beacon is at x=2, y=2
+",
         );
+        // -2..=2 and 2..=6 cover 9 positions, and one of them is the beacon
+        assert_eq!(no_beacons_in_row(&input, 2), 8);
+        assert_eq!(no_beacons_in_row(&input, 0), 9 + 4);
     }
-    result
-}
 
-pub fn part_1(input: &[Sensor]) -> i64 {
-    // let y = 20_000;
-    // sample is y = 10, real input is y = 20_000. Try to guess which one we have based on the input length
-    let y = if input.len() == 14 { 10 } else { 2_000_000 };
-    // let's convert (pos, pos) to (pos, radius)
-    let circles = input.iter().map(|Sensor(pos, closest)| {
-        let radius =
-            (pos.0 as i64 - closest.0 as i64).abs() + (pos.1 as i64 - closest.1 as i64).abs();
-        Circle(*pos, radius)
-    });
-    // let's try printing the whole map from -5..25
-    // for y in -5..25 {
-    //     print!("{:2} ", y);
-    //     let ranges =
-    //         overlapping_intervals(&circles.clone().map(|c| range_in(y, &c)).collect::<Vec<_>>());
-    //     for x in -5..25 {
-    //         // let c = circles.clone().find(|c| range_in(y, c).contains(&x));
-    //         let c = ranges.iter().find(|r| r.contains(&x));
-    //         print!(
-    //             "{}",
-    //             match c {
-    //                 Some(_) => '#',
-    //                 None => '.',
-    //             }
-    //         );
-    //     }
-    //     println!();
-    // }
-    let ranges = overlapping_intervals(&circles.map(|c| range_in(y, &c)).collect::<Vec<_>>());
-    ranges.iter().map(|r| r.end() - r.start()).sum::<i64>()
-        - (input.iter().filter(|Sensor(_, pos)| pos.1 == y).count() as i64)
+    #[test]
+    fn test_sensor_field() {
+        let input = generator(SAMPLE);
+        let field = SensorField::new(&input);
+        assert_eq!(field.sensors().len(), 14);
+        // "Sensor at x=8, y=7: closest beacon is at x=2, y=10"
+        assert_eq!(field.sensors()[6].position(), (8, 7));
+        assert_eq!(field.sensors()[6].beacon(), (2, 10));
+        assert_eq!(field.radius(6), 9);
+
+        assert_eq!(field.covering((8, 7)), vec![6]);
+        assert!(field.is_covered((2, 10)));
+        assert!(!field.is_covered((14, 11)));
+        assert!(field.covering((14, 11)).is_empty());
+        for y in -5..=25 {
+            for x in -5..=25 {
+                let covering = field.covering((x, y));
+                assert_eq!(field.is_covered((x, y)), !covering.is_empty());
+                for (i, sensor) in input.iter().enumerate() {
+                    let (sx, sy) = sensor.position();
+                    let distance = (x - sx).abs() + (y - sy).abs();
+                    assert_eq!(covering.contains(&i), distance <= field.radius(i));
+                }
+            }
+        }
+
+        assert_eq!(field.nearest((8, 7)), Some((6, 0)));
+        assert_eq!(field.nearest((14, 11)), Some((3, 5)));
+        // (11, 17) is 3 from both x=9, y=16 and x=14, y=17
+        assert_eq!(field.nearest((11, 17)), Some((1, 3)));
+        assert_eq!(field.nearest((10, 18)), Some((4, 2)));
+        assert_eq!(SensorField::new(&[]).nearest((0, 0)), None);
+
+        assert_eq!(
+            field.uncovered_in(0..=20, 0..=20).collect::<Vec<_>>(),
+            vec![(14, 11)]
+        );
+        let uncovered = field.uncovered_in(-10..=30, -10..=30).collect::<Vec<_>>();
+        let expected = (-10..=30)
+            .flat_map(|y| (-10..=30).map(move |x| (x, y)))
+            .filter(|&p| !field.is_covered(p))
+            .collect::<Vec<_>>();
+        assert_eq!(uncovered, expected);
+    }
 }
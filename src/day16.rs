@@ -209,7 +209,7 @@ pub fn part_1(input: &Vec<IndexValve>) -> usize {
 }
 
 // instead of a graph search, find each valid permutation of valves and calculate the flow
-pub(crate) fn part_1_permutations(input: &Vec<IndexValve>) -> usize {
+pub fn part_1_permutations(input: &Vec<IndexValve>) -> usize {
     let costs = floyd_warshall(input);
     let non_zero_nodes = input
         .iter()
@@ -0,0 +1,275 @@
+use std::ops::RangeInclusive;
+
+// a set of i64s, stored as sorted, disjoint ranges. Ranges that overlap or touch (like 1..=3
+// and 4..=6) are merged, so there's only ever one way to store a given set
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct IntervalSet {
+    // (start, end), both inclusive
+    ranges: Vec<(i64, i64)>,
+}
+
+impl IntervalSet {
+    pub fn new() -> IntervalSet {
+        IntervalSet::default()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ranges.is_empty()
+    }
+
+    // how many numbers are in the set. Only the set of every i64 is too big for this
+    pub fn len(&self) -> u64 {
+        self.ranges
+            .iter()
+            .map(|&(start, end)| end.abs_diff(start) + 1)
+            .sum()
+    }
+
+    pub fn contains(&self, x: i64) -> bool {
+        let i = self.ranges.partition_point(|&(_, end)| end < x);
+        self.ranges.get(i).is_some_and(|&(start, _)| start <= x)
+    }
+
+    // an empty range (start > end) doesn't change anything
+    pub fn insert(&mut self, range: RangeInclusive<i64>) {
+        let (mut start, mut end) = range.into_inner();
+        if start > end {
+            return;
+        }
+        // everything from `lo` up to (not including) `hi` overlaps or touches the new range
+        let lo = self
+            .ranges
+            .partition_point(|&(_, e)| e < start.saturating_sub(1));
+        let hi = self
+            .ranges
+            .partition_point(|&(s, _)| s <= end.saturating_add(1));
+        if lo < hi {
+            start = start.min(self.ranges[lo].0);
+            end = end.max(self.ranges[hi - 1].1);
+        }
+        self.ranges.splice(lo..hi, [(start, end)]);
+    }
+
+    pub fn insert_point(&mut self, x: i64) {
+        self.insert(x..=x);
+    }
+
+    pub fn union(&mut self, other: &IntervalSet) {
+        for range in other.iter() {
+            self.insert(range);
+        }
+    }
+
+    pub fn remove(&mut self, range: RangeInclusive<i64>) {
+        let (start, end) = range.into_inner();
+        if start > end {
+            return;
+        }
+        // everything from `lo` up to (not including) `hi` overlaps the range
+        let lo = self.ranges.partition_point(|&(_, e)| e < start);
+        let hi = self.ranges.partition_point(|&(s, _)| s <= end);
+        let mut left = vec![];
+        if lo < hi {
+            let (first, _) = self.ranges[lo];
+            if first < start {
+                left.push((first, start - 1));
+            }
+            let (_, last) = self.ranges[hi - 1];
+            if last > end {
+                left.push((end + 1, last));
+            }
+        }
+        self.ranges.splice(lo..hi, left);
+    }
+
+    pub fn remove_point(&mut self, x: i64) {
+        self.remove(x..=x);
+    }
+
+    pub fn subtract(&mut self, other: &IntervalSet) {
+        for range in other.iter() {
+            self.remove(range);
+        }
+    }
+
+    // the ranges in the set, in order
+    pub fn iter(&self) -> impl Iterator<Item = RangeInclusive<i64>> + '_ {
+        self.ranges.iter().map(|&(start, end)| start..=end)
+    }
+
+    // the ranges between the ones in the set, in order. There's nothing before the first one,
+    // or after the last one
+    pub fn gaps(&self) -> impl Iterator<Item = RangeInclusive<i64>> + '_ {
+        self.ranges
+            .windows(2)
+            .map(|pair| pair[0].1 + 1..=pair[1].0 - 1)
+    }
+
+    // the numbers in `bounds` that aren't in the set, as ranges
+    pub fn gaps_in(&self, bounds: RangeInclusive<i64>) -> IntervalSet {
+        let mut gaps = IntervalSet::new();
+        gaps.insert(bounds);
+        gaps.subtract(self);
+        gaps
+    }
+}
+
+impl FromIterator<RangeInclusive<i64>> for IntervalSet {
+    fn from_iter<I: IntoIterator<Item = RangeInclusive<i64>>>(iter: I) -> IntervalSet {
+        let mut set = IntervalSet::new();
+        set.extend(iter);
+        set
+    }
+}
+
+impl Extend<RangeInclusive<i64>> for IntervalSet {
+    fn extend<I: IntoIterator<Item = RangeInclusive<i64>>>(&mut self, iter: I) {
+        for range in iter {
+            self.insert(range);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeSet;
+
+    fn ranges(set: &IntervalSet) -> Vec<RangeInclusive<i64>> {
+        set.iter().collect()
+    }
+
+    #[test]
+    fn test_insert() {
+        let mut set = IntervalSet::new();
+        assert!(set.is_empty());
+        set.insert(1..=3);
+        set.insert(10..=12);
+        let (start, end) = (5, 4);
+        set.insert(start..=end);
+        assert_eq!(ranges(&set), vec![1..=3, 10..=12]);
+        // touching ranges merge
+        set.insert(4..=6);
+        assert_eq!(ranges(&set), vec![1..=6, 10..=12]);
+        set.insert_point(8);
+        assert_eq!(ranges(&set), vec![1..=6, 8..=8, 10..=12]);
+        set.insert(0..=20);
+        assert_eq!(ranges(&set), vec![0..=20]);
+        assert_eq!(set.len(), 21);
+        assert!(set.contains(0) && set.contains(20) && !set.contains(21) && !set.contains(-1));
+    }
+
+    #[test]
+    fn test_remove() {
+        let mut set = [0..=10, 20..=30].into_iter().collect::<IntervalSet>();
+        set.remove_point(5);
+        set.remove_point(5);
+        set.remove(9..=21);
+        assert_eq!(ranges(&set), vec![0..=4, 6..=8, 22..=30]);
+        assert_eq!(set.len(), 5 + 3 + 9);
+        assert_eq!(ranges(&set.gaps_in(-2..=25)), vec![-2..=-1, 5..=5, 9..=21]);
+        assert_eq!(set.gaps().collect::<Vec<_>>(), vec![5..=5, 9..=21]);
+        set.remove(-100..=100);
+        assert!(set.is_empty());
+    }
+
+    #[test]
+    fn test_extremes() {
+        let mut set = IntervalSet::new();
+        set.insert(i64::MIN..=i64::MIN + 1);
+        set.insert(i64::MAX - 1..=i64::MAX);
+        set.insert_point(0);
+        assert_eq!(set.len(), 5);
+        assert!(set.contains(i64::MIN) && set.contains(i64::MAX));
+        set.remove_point(i64::MIN);
+        set.remove_point(i64::MAX);
+        assert_eq!(
+            ranges(&set),
+            vec![
+                i64::MIN + 1..=i64::MIN + 1,
+                0..=0,
+                i64::MAX - 1..=i64::MAX - 1
+            ]
+        );
+        set.insert(i64::MIN..=-1);
+        set.insert(1..=i64::MAX - 2);
+        assert_eq!(ranges(&set), vec![i64::MIN..=i64::MAX - 1]);
+        assert_eq!(
+            set.gaps_in(i64::MIN..=i64::MAX),
+            [i64::MAX..=i64::MAX].into_iter().collect()
+        );
+    }
+
+    // random inserts and removes over a small range of numbers, checked against a BTreeSet
+    #[test]
+    fn test_against_btreeset() {
+        for seed in 0..200 {
+            let mut rng = oorandom::Rand64::new(seed);
+            let mut set = IntervalSet::new();
+            let mut model = BTreeSet::new();
+            for _ in 0..30 {
+                let start = rng.rand_range(0..40) as i64 - 20;
+                let end = start + rng.rand_range(0..12) as i64 - 2;
+                match rng.rand_range(0..4) {
+                    0 | 1 => {
+                        set.insert(start..=end);
+                        model.extend(start..=end);
+                    }
+                    2 => {
+                        set.remove(start..=end);
+                        model.retain(|x| !(start..=end).contains(x));
+                    }
+                    _ => {
+                        set.remove_point(start);
+                        model.remove(&start);
+                    }
+                }
+                assert_eq!(set.len(), model.len() as u64);
+                for x in -25..=25 {
+                    assert_eq!(set.contains(x), model.contains(&x), "{} in {:?}", x, set);
+                }
+                // sorted, and never overlapping or touching
+                let ranges = ranges(&set);
+                assert!(ranges.iter().all(|r| r.start() <= r.end()));
+                assert!(ranges.windows(2).all(|w| w[0].end() + 1 < *w[1].start()));
+                // the gaps are everything between the smallest and biggest number that's missing
+                let gaps = set.gaps().flatten().collect::<BTreeSet<_>>();
+                let missing = match (model.first(), model.last()) {
+                    (Some(&lo), Some(&hi)) => (lo..=hi).filter(|x| !model.contains(x)).collect(),
+                    _ => BTreeSet::new(),
+                };
+                assert_eq!(gaps, missing);
+                let outside = set
+                    .gaps_in(-25..=25)
+                    .iter()
+                    .flatten()
+                    .collect::<BTreeSet<_>>();
+                assert_eq!(outside, (-25..=25).filter(|x| !model.contains(x)).collect());
+            }
+        }
+    }
+
+    #[test]
+    fn test_union_and_subtract() {
+        for seed in 0..50 {
+            let mut rng = oorandom::Rand64::new(seed);
+            let mut random_set = || {
+                (0..5)
+                    .map(|_| {
+                        let start = rng.rand_range(0..40) as i64 - 20;
+                        start..=start + rng.rand_range(0..8) as i64
+                    })
+                    .collect::<IntervalSet>()
+            };
+            let (a, b) = (random_set(), random_set());
+            let mut union = a.clone();
+            union.union(&b);
+            let mut difference = a.clone();
+            difference.subtract(&b);
+            for x in -25..=30 {
+                assert_eq!(union.contains(x), a.contains(x) || b.contains(x));
+                assert_eq!(difference.contains(x), a.contains(x) && !b.contains(x));
+            }
+        }
+    }
+}
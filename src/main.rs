@@ -1,19 +1,12 @@
-mod day11;
-mod day12;
-mod day13;
-mod day14;
-mod day15;
-mod day16;
-mod day17;
-mod day18;
+use aoc_2022_rust::*;
 
 aoc_main::main! {
     year 2022;
     day11 : generator => part_1, part_2, part_2_rayon;
-    day12 : generator => part_1, part_1_dijkstras, part_2;
-    day13 : generator => part_1, part_2;
-    day14 : generator => part_1, part_1_backtracking, part_2, part_2_backtracking;
-    day15 : generator => part_1;
+    day12 : generator? => part_1, part_1_dijkstras, part_1_bidirectional, part_2;
+    day13 : generator? => part_1, part_1_streaming, part_2?, part_2_arena?;
+    day14 : generator => part_1, part_1_backtracking, part_2, part_2_backtracking, part_2_rows, water;
+    day15 : generator => part_1, part_2;
     day16 : generator => part_1, part_1_permutations, part_2;
     day17 : generator => part_1;
     day18 : generator => part_1, part_1_alt, part_2;